@@ -0,0 +1,241 @@
+use std::time::{Duration, Instant};
+
+use crate::simulate::*;
+use crate::stats::*;
+
+pub const USAGE: &str = "usage: ar --bench [--ticks <n>] [--sizes <n,..>] [--threads <n,..>] [--scenes <name,..>]
+threads are chunks and have to be square numbers, scenes are air, half_water, sand_pile, lava_water";
+
+//a standard starting grid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scene {
+    //nothing but air
+    Air,
+    //the top half full of water, falling onto nothing
+    HalfWater,
+    //a block of sand in the middle third that falls and piles up
+    SandPile,
+    //stripes of lava and water under air, reacting everywhere they touch
+    LavaWater,
+}
+
+impl Scene {
+    pub const ALL: [Scene; 4] = [
+        Scene::Air,
+        Scene::HalfWater,
+        Scene::SandPile,
+        Scene::LavaWater,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scene::Air => "air",
+            Scene::HalfWater => "half_water",
+            Scene::SandPile => "sand_pile",
+            Scene::LavaWater => "lava_water",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Scene> {
+        Scene::ALL.into_iter().find(|scene| scene.name() == name)
+    }
+
+    pub fn build(&self, size: u32, chunk_div: u32) -> Simulation {
+        let mut sim = Simulation::with_size([size, size], chunk_div);
+        for y in 0..size {
+            for x in 0..size {
+                let typ = match self {
+                    Scene::Air => continue,
+                    Scene::HalfWater if y < size / 2 => "water",
+                    Scene::SandPile
+                        if x >= size / 3 && x < size * 2 / 3 && y >= size / 6 && y < size / 2 =>
+                    {
+                        "sand"
+                    }
+                    Scene::LavaWater if y >= size / 2 => {
+                        if (x / 4) % 2 == 0 {
+                            "lava"
+                        } else {
+                            "water"
+                        }
+                    }
+                    _ => continue,
+                };
+                sim.set_pixel([x, y], typ.to_string());
+            }
+        }
+        sim
+    }
+}
+
+//what one scene at one size and thread count did
+pub struct BenchResult {
+    pub scene: Scene,
+    pub size: u32,
+    pub threads: u32,
+    pub ticks: u64,
+    pub elapsed: Duration,
+    //summed over every tick
+    pub times: [Duration; 6],
+}
+
+impl BenchResult {
+    pub fn header() -> String {
+        let mut cols = vec!["scene", "size", "threads", "ticks", "ticks_per_sec"]
+            .into_iter()
+            .map(|col| col.to_string())
+            .collect::<Vec<String>>();
+        cols.extend(PhaseTimes::NAMES.iter().map(|name| format!("{}_ms", name)));
+        cols.join(",")
+    }
+
+    //phase times are the mean per tick
+    pub fn row(&self) -> String {
+        let mut cols = vec![
+            self.scene.name().to_string(),
+            self.size.to_string(),
+            self.threads.to_string(),
+            self.ticks.to_string(),
+            format!("{:.1}", self.ticks as f64 / self.elapsed.as_secs_f64()),
+        ];
+        for time in self.times {
+            cols.push(format!(
+                "{:.3}",
+                time.as_secs_f64() * 1000.0 / self.ticks as f64
+            ));
+        }
+        cols.join(",")
+    }
+}
+
+//every scene at every size and thread count
+pub struct Bench {
+    pub ticks: u64,
+    pub sizes: Vec<u32>,
+    pub threads: Vec<u32>,
+    pub scenes: Vec<Scene>,
+}
+
+impl Bench {
+    pub fn parse(args: &[String]) -> Result<Bench, String> {
+        let mut bench = Bench {
+            ticks: 100,
+            sizes: vec![40, 80, 160],
+            threads: vec![1, 4, 16],
+            scenes: Scene::ALL.to_vec(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", arg))
+            };
+            match arg.as_str() {
+                "--bench" => {}
+                "--ticks" => {
+                    bench.ticks = value()?
+                        .parse()
+                        .map_err(|_| "ticks must be a number".to_string())?;
+                }
+                "--sizes" => bench.sizes = number_list(&value()?, "sizes")?,
+                "--threads" => bench.threads = number_list(&value()?, "threads")?,
+                "--scenes" => {
+                    bench.scenes = value()?
+                        .split(',')
+                        .map(|name| Scene::parse(name).ok_or(format!("unknown scene {}", name)))
+                        .collect::<Result<Vec<Scene>, String>>()?;
+                }
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+        if bench.ticks == 0 {
+            return Err("ticks must be more than 0".to_string());
+        }
+        for &threads in &bench.threads {
+            let n = (threads as f64).sqrt() as u32;
+            if threads == 0 || n * n != threads {
+                return Err(format!("{} threads is not a square number", threads));
+            }
+            for &size in &bench.sizes {
+                if size == 0 || size % n != 0 {
+                    return Err(format!("size {} can't be split {} ways", size, threads));
+                }
+            }
+        }
+        Ok(bench)
+    }
+
+    pub fn run(&self) -> Result<(), String> {
+        println!("{}", BenchResult::header());
+        for &scene in &self.scenes {
+            for &size in &self.sizes {
+                for &threads in &self.threads {
+                    let result = run_scene(scene, size, threads, self.ticks);
+                    println!("{}", result.row());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn number_list(list: &str, what: &str) -> Result<Vec<u32>, String> {
+    list.split(',')
+        .map(|n| n.parse().map_err(|_| format!("{} must be numbers", what)))
+        .collect()
+}
+
+pub fn run_scene(scene: Scene, size: u32, threads: u32, ticks: u64) -> BenchResult {
+    let mut sim = scene.build(size, threads);
+    let mut times = [Duration::ZERO; 6];
+    let start = Instant::now();
+    for _ in 0..ticks {
+        sim.step();
+        for (total, time) in times.iter_mut().zip(sim.stats.times.list()) {
+            *total += time;
+        }
+    }
+    BenchResult {
+        scene,
+        size,
+        threads,
+        ticks,
+        elapsed: start.elapsed(),
+        times,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scenes_fill_what_they_say() {
+        let sim = Scene::LavaWater.build(16, 4);
+        let counts = sim.count_elements();
+        let code = |name: &str| sim.elements._get(name.to_string()) as usize;
+        assert_eq!(counts[code("lava")] + counts[code("water")], 16 * 8);
+        assert_eq!(counts[code("air")], 16 * 8);
+
+        let result = run_scene(Scene::HalfWater, 16, 1, 3);
+        assert_eq!(
+            result.row().split(',').count(),
+            BenchResult::header().split(',').count()
+        );
+        assert!(result.times.iter().sum::<Duration>() <= result.elapsed);
+    }
+
+    #[test]
+    fn bad_settings_are_refused() {
+        let args = |text: &str| {
+            text.split(' ')
+                .map(|arg| arg.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert!(Bench::parse(&args("--bench --threads 1,9 --sizes 18")).is_ok());
+        assert!(Bench::parse(&args("--bench --threads 3")).is_err());
+        assert!(Bench::parse(&args("--bench --threads 4 --sizes 41")).is_err());
+        assert!(Bench::parse(&args("--bench --scenes air,mud")).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//how close in the camera can get, in screen pixels per cell
+pub const MIN_ZOOM: f64 = 1.0;
+pub const MAX_ZOOM: f64 = 64.0;
+
+//which part of the world is on screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    //world position at the top left of the window, in cells
+    pub offset: [f64; 2],
+    //screen pixels per cell
+    pub zoom: f64,
+    //window size in screen pixels
+    pub window: [f64; 2],
+}
+
+impl Camera {
+    pub fn new(window: [f64; 2], zoom: f64) -> Camera {
+        Camera {
+            offset: [0.0; 2],
+            zoom,
+            window,
+        }
+    }
+
+    pub fn world_at(&self, screen: [f64; 2]) -> [f64; 2] {
+        [
+            screen[0] / self.zoom + self.offset[0],
+            screen[1] / self.zoom + self.offset[1],
+        ]
+    }
+
+    pub fn screen_at(&self, world: [f64; 2]) -> [f64; 2] {
+        [
+            (world[0] - self.offset[0]) * self.zoom,
+            (world[1] - self.offset[1]) * self.zoom,
+        ]
+    }
+
+    //the cell under a screen position, if there is one
+    pub fn cell_at(&self, screen: [f64; 2], size: [u32; 2]) -> Option<[u32; 2]> {
+        let world = self.world_at(screen);
+        if 0.0 <= world[0]
+            && world[0] < size[0] as f64
+            && 0.0 <= world[1]
+            && world[1] < size[1] as f64
+        {
+            Some([world[0] as u32, world[1] as u32])
+        } else {
+            None
+        }
+    }
+
+    //top left and bottom right corners of a cell on screen
+    pub fn cell_rect(&self, pos: [u32; 2]) -> [f64; 4] {
+        let tl = self.screen_at([pos[0] as f64, pos[1] as f64]);
+        [tl[0], tl[1], tl[0] + self.zoom, tl[1] + self.zoom]
+    }
+
+    //move the view by a distance in screen pixels
+    pub fn pan(&mut self, delta: [f64; 2]) {
+        self.offset[0] -= delta[0] / self.zoom;
+        self.offset[1] -= delta[1] / self.zoom;
+    }
+
+    //zoom by `factor` keeping the world point under `screen` where it is
+    pub fn zoom_at(&mut self, screen: [f64; 2], factor: f64) {
+        let anchor = self.world_at(screen);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = [
+            anchor[0] - screen[0] / self.zoom,
+            anchor[1] - screen[1] / self.zoom,
+        ];
+    }
+
+    //cells that are at least partly on screen, [x0, y0, x1, y1) clamped to the grid
+    pub fn visible(&self, size: [u32; 2]) -> [u32; 4] {
+        let tl = self.world_at([0.0, 0.0]);
+        let br = self.world_at(self.window);
+        let x0 = tl[0].floor().clamp(0.0, size[0] as f64) as u32;
+        let y0 = tl[1].floor().clamp(0.0, size[1] as f64) as u32;
+        let x1 = br[0].ceil().clamp(0.0, size[0] as f64) as u32;
+        let y1 = br[1].ceil().clamp(0.0, size[1] as f64) as u32;
+        [x0, y0, x1.max(x0), y1.max(y0)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_and_world_round_trip() {
+        let mut camera = Camera::new([400.0, 300.0], 10.0);
+        camera.pan([-55.0, 20.0]);
+        let world = camera.world_at([123.0, 45.0]);
+        let screen = camera.screen_at(world);
+        assert!((screen[0] - 123.0).abs() < 1e-9 && (screen[1] - 45.0).abs() < 1e-9);
+        assert_eq!(camera.cell_at([0.0, 0.0], [100, 100]), None);
+        assert_eq!(camera.cell_at([200.0, 100.0], [100, 100]), Some([25, 8]));
+    }
+
+    #[test]
+    fn zoom_keeps_the_cursor_still() {
+        let mut camera = Camera::new([400.0, 400.0], 10.0);
+        let before = camera.world_at([150.0, 250.0]);
+        camera.zoom_at([150.0, 250.0], 2.0);
+        let after = camera.world_at([150.0, 250.0]);
+        assert_eq!(camera.zoom, 20.0);
+        assert!((before[0] - after[0]).abs() < 1e-9 && (before[1] - after[1]).abs() < 1e-9);
+
+        camera.zoom_at([0.0, 0.0], 1000.0);
+        assert_eq!(camera.zoom, MAX_ZOOM);
+    }
+
+    #[test]
+    fn only_cells_on_screen_are_visible() {
+        let mut camera = Camera::new([400.0, 400.0], 10.0);
+        assert_eq!(camera.visible([1000, 20]), [0, 0, 40, 20]);
+        //half a cell in, the partly covered cell still counts
+        camera.offset = [100.5, -3.0];
+        assert_eq!(camera.visible([1000, 20]), [100, 0, 141, 20]);
+        camera.offset = [2000.0, 0.0];
+        let [x0, _, x1, _] = camera.visible([1000, 20]);
+        assert_eq!(x0, x1);
+    }
+}
@@ -1,6 +1,12 @@
+use piston_window::texture::{CreateTexture, Format, UpdateTexture};
 use piston_window::*;
 use rand::Rng;
 
+use crate::camera::*;
+use crate::elements::*;
+use crate::grid::Grid;
+use crate::hud::*;
+use crate::overlay::*;
 use crate::pixel::*;
 
 use crate::simulate::*;
@@ -37,14 +43,6 @@ pub fn rect_pos(pos1: [u32; 2], pos2: [u32; 2]) -> [u32; 4] {
     [x1, y1, x2, y2]
 }
 
-pub fn screem(pos: [u32; 2], scale: u32) -> [f64; 4] {
-    let tx = (pos[0] * scale) as f64;
-    let ty = (pos[1] * scale) as f64;
-    let by = ty + scale as f64;
-    let lx = tx + scale as f64;
-    [tx, ty, lx, by]
-}
-
 pub fn draw_cursor_outline(pos: [f64; 4], context: Context, graphics: &mut G2d) {
     let tx = pos[0];
     let ty = pos[1];
@@ -55,18 +53,18 @@ pub fn draw_cursor_outline(pos: [f64; 4], context: Context, graphics: &mut G2d)
     let line2 = [tx, ty, tx, by];
     let line3 = [tx, by, lx, by];
     let line4 = [lx, ty, lx, by];
-    for (_i, line) in [line, line2, line3, line4].iter().enumerate() {
+    for line in [line, line2, line3, line4].iter() {
         let lined = line::Line::new([0.0, 0.0, 0.0, 1.0], 1.0);
         lined.draw(*line, &context.draw_state, context.transform, graphics);
     }
 }
 
-pub fn get_screen_edge(pos: [u32; 4], scale: u32) -> [f64; 4] {
+pub fn get_screen_edge(pos: [u32; 4], camera: &Camera) -> [f64; 4] {
     //get top left and bottom right corners
     //use rect_pos to get the top left and bottom right corners
     let pos2 = rect_pos([pos[0], pos[1]], [pos[2], pos[3]]);
-    let topleft = screem([pos2[0], pos2[1]], scale);
-    let bottomright = screem([pos2[2], pos2[3]], scale);
+    let topleft = camera.cell_rect([pos2[0], pos2[1]]);
+    let bottomright = camera.cell_rect([pos2[2], pos2[3]]);
     [topleft[0], topleft[1], bottomright[2], bottomright[3]]
 }
 
@@ -80,29 +78,174 @@ pub fn draw_outline(pos: [f64; 4], context: Context, graphics: &mut G2d) {
     let line2 = [tx, ty, tx, by];
     let line3 = [tx, by, lx, by];
     let line4 = [lx, ty, lx, by];
-    for (_i, line) in [line, line2, line3, line4].iter().enumerate() {
+    for line in [line, line2, line3, line4].iter() {
         let lined = line::Line::new([0.0, 0.0, 0.0, 1.0], 1.0);
         lined.draw(*line, &context.draw_state, context.transform, graphics);
     }
 }
 
+//a colour per cell as one texture, a texel per cell, drawn as a single quad,
+//the grid and the heatmap each have one
+pub struct GridTexture {
+    texture: G2dTexture,
+    context: G2dTextureContext,
+    size: [u32; 2],
+    //what the texture holds right now, rgba per cell
+    uploaded: Vec<u8>,
+}
+
+impl GridTexture {
+    pub fn new(window: &mut PistonWindow, size: [u32; 2], rgba: Vec<u8>) -> GridTexture {
+        let mut context = window.create_texture_context();
+        let uploaded = rgba;
+        let texture = create_grid_texture(&mut context, &uploaded, size);
+        GridTexture {
+            texture,
+            context,
+            size,
+            uploaded,
+        }
+    }
+
+    //upload the cells that changed colour since last time
+    pub fn update(&mut self, size: [u32; 2], rgba: Vec<u8>) {
+        if size != self.size {
+            //a different sized world was loaded, start over
+            self.texture = create_grid_texture(&mut self.context, &rgba, size);
+            self.size = size;
+        } else if let Some(rect) = dirty_rect(&self.uploaded, &rgba, size[0]) {
+            let region = rgba_region(&rgba, size[0], rect);
+            //the trait method, the texture's own update only takes whole images
+            UpdateTexture::update(
+                &mut self.texture,
+                &mut self.context,
+                Format::Rgba8,
+                &region,
+                [rect[0], rect[1]],
+                [rect[2] - rect[0], rect[3] - rect[1]],
+            )
+            .unwrap();
+        }
+        self.uploaded = rgba;
+    }
+
+    pub fn draw(
+        &mut self,
+        camera: &Camera,
+        context: Context,
+        graphics: &mut G2d,
+        device: &mut GfxDevice,
+    ) {
+        //send the pending uploads before drawing with them
+        self.context.encoder.flush(device);
+        let tl = camera.screen_at([0.0, 0.0]);
+        Image::new()
+            .rect([
+                tl[0],
+                tl[1],
+                self.size[0] as f64 * camera.zoom,
+                self.size[1] as f64 * camera.zoom,
+            ])
+            .draw(
+                &self.texture,
+                &context.draw_state,
+                context.transform,
+                graphics,
+            );
+    }
+}
+
+fn create_grid_texture(context: &mut G2dTextureContext, rgba: &[u8], size: [u32; 2]) -> G2dTexture {
+    //nearest so cells stay sharp squares however far in the camera is
+    let settings = TextureSettings::new().filter(Filter::Nearest);
+    G2dTexture::create(context, Format::Rgba8, rgba, size, &settings).unwrap()
+}
+
+//cell colours as rgba bytes, row by row
+pub fn grid_rgba(grid: &Grid, elements: &ElementList) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(grid.ptype.len() * 4);
+    for i in 0..grid.ptype.len() {
+        for c in grid.color(i, elements) {
+            rgba.push((c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+    }
+    rgba
+}
+
+//bounding box [x0, y0, x1, y1) of the cells that differ between two rgba buffers
+pub fn dirty_rect(old: &[u8], new: &[u8], width: u32) -> Option<[u32; 4]> {
+    let mut rect: Option<[u32; 4]> = None;
+    for (i, (a, b)) in old.chunks(4).zip(new.chunks(4)).enumerate() {
+        if a == b {
+            continue;
+        }
+        let x = i as u32 % width;
+        let y = i as u32 / width;
+        rect = Some(match rect {
+            Some(r) => [r[0].min(x), r[1].min(y), r[2].max(x + 1), r[3].max(y + 1)],
+            None => [x, y, x + 1, y + 1],
+        });
+    }
+    rect
+}
+
+//copy the cells inside `rect` out of an rgba buffer
+pub fn rgba_region(rgba: &[u8], width: u32, rect: [u32; 4]) -> Vec<u8> {
+    let mut region = vec![];
+    for y in rect[1]..rect[3] {
+        let start = ((y * width + rect[0]) * 4) as usize;
+        let end = ((y * width + rect[2]) * 4) as usize;
+        region.extend_from_slice(&rgba[start..end]);
+    }
+    region
+}
+
+//everything drawn over the grid, bottom to top
+pub struct Layers<'a> {
+    pub overlays: &'a Overlays,
+    //which cursor to draw and where
+    pub case: u8,
+    pub pos: [u32; 4],
+    pub hud: &'a mut Hud,
+    //only updated while a heatmap is showing
+    pub heatmap: &'a mut GridTexture,
+}
+
 pub fn new_frame(
     window: &mut PistonWindow,
     event: &Event,
-    pixels: &Vec<Vec<Pixel>>,
-    scale: u32,
-    case: u8,
-    pos: [u32; 4],
-    size: [u32; 2],
+    sim: &Simulation,
+    grid_texture: &mut GridTexture,
+    camera: &Camera,
+    layers: Layers,
 ) {
-    window.draw_2d(event, |context, graphics, _| {
+    let Layers {
+        overlays,
+        case,
+        pos,
+        hud,
+        heatmap,
+    } = layers;
+    let size = sim.size;
+    grid_texture.update(size, grid_rgba(&sim.grid, &sim.elements));
+    if overlays.heatmap != Heatmap::Off {
+        heatmap.update(size, heatmap_rgba(&sim.grid, overlays.heatmap));
+    }
+    window.draw_2d(event, |context, graphics, device| {
         clear([1.0; 4], graphics);
-        //pixel.pixel_draw
-        pixel_draw(&*pixels, context, graphics, scale);
+        grid_texture.draw(camera, context, graphics, device);
+        //outlines round dense resting cells, its own pass since it's slow
+        if overlays.outlines {
+            outline_draw(&sim.grid, context, graphics, camera);
+        }
+        if overlays.heatmap != Heatmap::Off {
+            heatmap.draw(camera, context, graphics, device);
+        }
+        overlay_draw(sim, overlays, camera, context, graphics);
         //draw cursor
         if case == 1 {
             //holding left click, draw from last click to mouse pos
-            draw_outline(get_screen_edge(pos, scale), context, graphics);
+            draw_outline(get_screen_edge(pos, camera), context, graphics);
         } else if case == 2 {
             //radius
             let radius_vec = radius([pos[0], pos[1]], pos[2], size);
@@ -128,26 +271,21 @@ pub fn new_frame(
 
             //just draw the bounding box for now
             draw_outline(
-                get_screen_edge([bb_x[0], bb_y[0], bb_x[1], bb_y[1]], scale),
+                get_screen_edge([bb_x[0], bb_y[0], bb_x[1], bb_y[1]], camera),
                 context,
                 graphics,
             );
         } else if case == 3 {
             //line from edge to edge at y height
-            draw_outline(
-                [
-                    0.0,
-                    pos[1] as f64 * scale as f64,
-                    size[0] as f64 * scale as f64,
-                    pos[1] as f64 * scale as f64 + scale as f64,
-                ],
-                context,
-                graphics,
-            );
+            let tl = camera.screen_at([0.0, pos[1] as f64]);
+            let br = camera.screen_at([size[0] as f64, pos[1] as f64 + 1.0]);
+            draw_outline([tl[0], tl[1], br[0], br[1]], context, graphics);
         } else if case == 4 {
             //draw cursor outline
-            draw_cursor_outline(screem([pos[0], pos[1]], scale), context, graphics);
+            draw_cursor_outline(camera.cell_rect([pos[0], pos[1]]), context, graphics);
         }
+        //hud on top of everything
+        hud.draw(context, graphics, device);
     });
 }
 
@@ -158,13 +296,8 @@ pub fn _rand_color_margin(color: [f32; 4], margin: f32) -> [f32; 4] {
         new_color[i] = rng.gen_range((color[i] - margin)..(color[i] + margin));
     }
     //max and min
-    for i in 0..3 {
-        if new_color[i] > 1.0 {
-            new_color[i] = 1.0;
-        }
-        if new_color[i] < 0.0 {
-            new_color[i] = 0.0;
-        }
+    for c in new_color.iter_mut().take(3) {
+        *c = c.clamp(0.0, 1.0);
     }
     new_color[3] = 1.0;
     new_color
@@ -175,13 +308,8 @@ pub fn rand_color_grey(value: f32, margin: f32) -> [f32; 4] {
     let mut new_color = [rng.gen_range((value - margin)..(value + margin)); 4];
 
     //max and min
-    for i in 0..3 {
-        if new_color[i] > 1.0 {
-            new_color[i] = 1.0;
-        }
-        if new_color[i] < 0.0 {
-            new_color[i] = 0.0;
-        }
+    for c in new_color.iter_mut().take(3) {
+        *c = c.clamp(0.0, 1.0);
     }
     new_color[3] = 1.0;
     new_color
@@ -191,3 +319,26 @@ pub fn rand_color(value: f32, margin: f32) -> f32 {
     let mut rng = rand::thread_rng();
     rng.gen_range((value - margin)..(value + margin))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dirty_rect_covers_changed_cells() {
+        let old = vec![0u8; 4 * 4 * 3];
+        assert_eq!(dirty_rect(&old, &old, 4), None);
+
+        let mut new = old.clone();
+        //cells (1, 0) and (2, 2)
+        new[4] = 255;
+        new[(2 * 4 + 2) * 4 + 3] = 7;
+        let rect = dirty_rect(&old, &new, 4).unwrap();
+        assert_eq!(rect, [1, 0, 3, 3]);
+
+        let region = rgba_region(&new, 4, rect);
+        assert_eq!(region.len(), 2 * 3 * 4);
+        assert_eq!(region[0], 255);
+        assert_eq!(region[region.len() - 1], 7);
+    }
+}
@@ -1,7 +1,127 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::draw::*;
-use crate::pixel::*;
+use crate::fixed::{Fx, Movement};
+
+//how a pixel moves through the grid
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Phase {
+    //never moves and can't be pushed, only reactions and tools change it
+    Static,
+    //falls and piles up
+    Powder,
+    Liquid,
+    Gas,
+}
+
+impl Phase {
+    pub fn is_solid(self) -> bool {
+        self == Phase::Static || self == Phase::Powder
+    }
+}
+
+//colours each element comes in, cells pick one when they spawn
+pub const SHADES: usize = 16;
+
+//names of the elements, indexed by element code
+pub const NAMES: [&str; 14] = [
+    "default",   //0
+    "air",       //1
+    "sand",      //2
+    "water",     //3
+    "lava",      //4
+    "stone",     //5
+    "brick",     //6
+    "wood",      //7
+    "smoke",     //8
+    "glass",     //9
+    "fire",      //10
+    "gunpowder", //11
+    "emitter",   //12
+    "sink",      //13
+];
+
+//codes the rules refer to by name, each one indexes its own name in NAMES
+pub const AIR: u8 = 1;
+pub const SAND: u8 = 2;
+pub const WATER: u8 = 3;
+pub const LAVA: u8 = 4;
+pub const STONE: u8 = 5;
+pub const BRICK: u8 = 6;
+pub const WOOD: u8 = 7;
+pub const SMOKE: u8 = 8;
+pub const GLASS: u8 = 9;
+pub const FIRE: u8 = 10;
+pub const GUNPOWDER: u8 = 11;
+pub const EMITTER: u8 = 12;
+pub const SINK: u8 = 13;
+
+//what every pixel of an element has in common, the grid only keeps what
+//changes from one pixel to the next
+#[derive(Clone, Copy, Debug)]
+pub struct ElementProps {
+    pub code: u8,
+    pub density: f64,
+    pub min_force: f64,
+    pub gravity_multiplier: f64,
+    pub friction_multiplier: f64,
+    //how readily it catches fire once it's hot, 0.0 never burns
+    pub flammability: f64,
+    //heat given to each neighbour every tick while it burns
+    pub heat_output: u8,
+    //ticks a burning pixel lasts before its fuel is used up
+    pub burn_time: u8,
+    //ticks a new pixel burns for, rolled from this range, [0, 0] for ones that don't start alight
+    pub lifespan: [u8; 2],
+    //strength and radius of the explosion when it catches fire, 0.0 for non explosives
+    pub blast: f64,
+    pub phase: Phase,
+    //how many pixels a liquid spreads sideways when it can't fall, 0 for non liquids
+    pub dispersion: u8,
+    //chance a liquid holds still instead of spreading, thick liquids flow slower
+    pub viscosity: f64,
+    //chance a powder holds still instead of sliding off a slope, sticky powders pile steeper
+    pub stickiness: f64,
+    //one of the colours it comes in, at random
+    pub shade: fn() -> [f32; 4],
+    //the movement constants in fixed point, filled in by element()
+    pub fixed: Movement<Fx>,
+}
+
+impl ElementProps {
+    pub fn new(
+        code: u8,
+        density: f64,
+        min_force: f64,
+        gravity_multiplier: f64,
+        friction_multiplier: f64,
+    ) -> ElementProps {
+        ElementProps {
+            code,
+            density,
+            min_force,
+            gravity_multiplier,
+            friction_multiplier,
+            flammability: 0.0,
+            heat_output: 0,
+            burn_time: 0,
+            lifespan: [0, 0],
+            blast: 0.0,
+            phase: Phase::Static,
+            dispersion: 0,
+            viscosity: 0.0,
+            stickiness: 0.0,
+            shade: || [1.0, 0.0, 1.0, 1.0],
+            fixed: Movement::default(),
+        }
+    }
+
+    //can this element push other out of the way, solids never give way
+    pub fn displaces(&self, other: &ElementProps) -> bool {
+        self.code != other.code && self.density > other.density && !other.phase.is_solid()
+    }
+}
 
 //singletons of each element string and their number u8
 pub struct ElementList {
@@ -9,28 +129,22 @@ pub struct ElementList {
     pub element_codes: Vec<u8>,
     //              input, (catalyst, output)
     pub interactivity: HashMap<u8, Vec<(u8, u8)>>,
+    //indexed by element code, shared with every grid
+    pub props: Arc<[ElementProps]>,
+    pub shades: Vec<[[f32; 4]; SHADES]>,
+    //flammable things glow while they burn
+    pub embers: [[f32; 4]; SHADES],
 
     _lenght: usize,
 }
 
 impl ElementList {
     pub fn new() -> ElementList {
-        let elements = vec![
-            "default".to_string(), //0
-            "air".to_string(),     //1
-            "sand".to_string(),    //2
-            "water".to_string(),   //3
-            "lava".to_string(),    //4
-            "stone".to_string(),   //5
-            "brick".to_string(),   //6
-            "wood".to_string(),    //7
-            "smoke".to_string(),   //8
-            "glass".to_string(),   //9
-        ];
-        let element_codes = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let elements: Vec<String> = NAMES.iter().map(|name| name.to_string()).collect();
+        let element_codes = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13];
         let _lenght = elements.len();
 
-        let interactivity_list: [(u8, u8, u8); 5] = [
+        let interactivity_list: [(u8, u8, u8); 6] = [
             //water to air with lava
             (3, 4, 1),
             //lava to stone with water
@@ -39,39 +153,60 @@ impl ElementList {
             (4, 2, 8),
             //sand to glass with lava
             (2, 4, 9),
-            //wood to smoke with lava
-            (7, 4, 8),
+            //wood to fire with lava
+            (7, 4, 10),
+            //fire to smoke with water
+            (10, 3, 8),
         ];
 
         let mut interactivity: HashMap<u8, Vec<(u8, u8)>> = HashMap::new();
 
-        for i in 0..interactivity_list.len() {
-            let (input, catalyst, output) = interactivity_list[i];
-            if interactivity.contains_key(&input) {
-                interactivity
-                    .get_mut(&input)
-                    .unwrap()
-                    .push((catalyst, output));
-            } else {
-                interactivity.insert(input, vec![(catalyst, output)]);
+        for (input, catalyst, output) in interactivity_list {
+            interactivity
+                .entry(input)
+                .or_default()
+                .push((catalyst, output));
+        }
+
+        let props: Vec<ElementProps> = (0..elements.len() as u8).map(element).collect();
+        let mut shades = vec![];
+        for props in &props {
+            let mut colors = [[0.0; 4]; SHADES];
+            for color in colors.iter_mut() {
+                *color = (props.shade)();
             }
+            shades.push(colors);
+        }
+        let mut embers = [[0.0; 4]; SHADES];
+        for ember in embers.iter_mut() {
+            *ember = [rand_color(0.7, 0.1), rand_color(0.2, 0.1), 0.0, 1.0];
         }
 
         ElementList {
             elements,
             element_codes,
             interactivity,
+            props: props.into(),
+            shades,
+            embers,
             _lenght,
         }
     }
 
+    pub fn color(&self, code: u8, shade: u8, burning: bool) -> [f32; 4] {
+        if burning && self.props[code as usize].flammability > 0.0 {
+            return self.embers[shade as usize];
+        }
+        self.shades[code as usize][shade as usize]
+    }
+
     pub fn _get(&self, index: String) -> u8 {
         for i in 0..self._lenght {
             if self.elements[i] == index {
                 return self.element_codes[i];
             }
         }
-        return 0;
+        0
     }
 
     pub fn get_name(&self, index: u8) -> String {
@@ -80,7 +215,7 @@ impl ElementList {
                 return self.elements[i].clone();
             }
         }
-        return "default".to_string();
+        "default".to_string()
     }
 
     pub fn _len(&self) -> usize {
@@ -105,64 +240,148 @@ impl ElementList {
     }
 }
 
-pub fn glass(pos: [u32; 2]) -> Pixel {
-    let r = rand_color(0.5, 0.03);
-    let g = rand_color(0.5, 0.03);
-    let b = rand_color(0.65, 0.1);
-
-    Pixel::new(9, pos, [0.0; 2], [r, g, b, 1.0], 0.8, 1.1, 1.2, 0.99)
+//the table entry for an element code
+pub fn element(code: u8) -> ElementProps {
+    let mut props = match code {
+        AIR => air(),
+        SAND => sand(),
+        WATER => water(),
+        LAVA => lava(),
+        STONE => stone(),
+        BRICK => brick(),
+        WOOD => wood(),
+        SMOKE => smoke(),
+        GLASS => glass(),
+        FIRE => fire(),
+        GUNPOWDER => gunpowder(),
+        EMITTER => emitter(),
+        SINK => sink(),
+        //null element, should only be used to instantiate empty grid, then replaced
+        _ => ElementProps::new(0, 0.0, 0.0, 0.0, 0.0),
+    };
+    props.fixed = Movement::of(&props);
+    props
 }
 
-pub fn smoke(pos: [u32; 2]) -> Pixel {
-    let cl = rand_color_grey(0.6, 0.1);
-
-    Pixel::new(8, pos, [0.0; 2], cl, 0.01, 0.45, -1.5, 0.95)
+//spawns another element, what and how fast lives in Simulation::emitters
+pub fn emitter() -> ElementProps {
+    let mut props = ElementProps::new(EMITTER, 1.0, 2.0, 1.0, 0.99);
+    props.shade = || {
+        let b = rand_color(0.9, 0.05);
+        [0.2, b, b, 1.0]
+    };
+    props
 }
 
-pub fn wood(pos: [u32; 2]) -> Pixel {
-    let r = rand_color(0.5, 0.15);
-    let g = rand_color(0.3, 0.05);
-
-    Pixel::new(7, pos, [0.0; 2], [r, g, 0.0, 1.0], 0.4, 0.45, 1.2, 0.99)
+//deletes whatever flows into it
+pub fn sink() -> ElementProps {
+    let mut props = ElementProps::new(SINK, 1.0, 2.0, 1.0, 0.99);
+    props.shade = || rand_color_grey(0.02, 0.02);
+    props
 }
 
-pub fn air(pos: [u32; 2]) -> Pixel {
-    let cl = rand_color_grey(1.0, 0.05);
-
-    Pixel::new(1, pos, [0.0; 2], cl, 0.03, 0.0, 0.01, 0.95)
+pub fn gunpowder() -> ElementProps {
+    let mut props = ElementProps::new(GUNPOWDER, 0.7, 0.3, 1.2, 0.99);
+    props.shade = || rand_color_grey(0.25, 0.05);
+    props.phase = Phase::Powder;
+    props.stickiness = 0.4;
+    props.flammability = 0.6;
+    props.heat_output = 80;
+    props.burn_time = 1;
+    props.blast = 5.0;
+    props
 }
 
-pub fn sand(pos: [u32; 2]) -> Pixel {
-    let r = rand_color(0.8, 0.15);
-    let g = rand_color(0.8, 0.1);
+pub fn fire() -> ElementProps {
+    let mut props = ElementProps::new(FIRE, 0.02, 0.0, -1.2, 0.9);
+    props.shade = || [rand_color(0.95, 0.05), rand_color(0.5, 0.2), 0.0, 1.0];
+    props.phase = Phase::Gas;
+    props.heat_output = 40;
+    //flames only last a short while
+    props.lifespan = [6, 14];
+    props
+}
 
-    Pixel::new(2, pos, [0.0; 2], [r, g, 0.0, 1.0], 0.8, 0.4, 1.2, 0.99)
+pub fn glass() -> ElementProps {
+    let mut props = ElementProps::new(GLASS, 0.8, 1.1, 1.2, 0.99);
+    props.shade = || {
+        [
+            rand_color(0.5, 0.03),
+            rand_color(0.5, 0.03),
+            rand_color(0.65, 0.1),
+            1.0,
+        ]
+    };
+    props
 }
 
-pub fn water(pos: [u32; 2]) -> Pixel {
-    let b = rand_color(0.8, 0.15);
+pub fn smoke() -> ElementProps {
+    let mut props = ElementProps::new(SMOKE, 0.01, 0.45, -1.5, 0.95);
+    props.shade = || rand_color_grey(0.6, 0.1);
+    props.phase = Phase::Gas;
+    props
+}
 
-    Pixel::new(3, pos, [0.0; 2], [0.0, 0.0, b, 1.0], 0.5, 0.0, 1.0, 0.99)
+pub fn wood() -> ElementProps {
+    let mut props = ElementProps::new(WOOD, 0.4, 0.45, 1.2, 0.99);
+    props.shade = || [rand_color(0.5, 0.15), rand_color(0.3, 0.05), 0.0, 1.0];
+    props.flammability = 0.08;
+    props.heat_output = 30;
+    props.burn_time = 60;
+    props
 }
 
-pub fn lava(pos: [u32; 2]) -> Pixel {
-    let r = rand_color(0.8, 0.15);
+pub fn air() -> ElementProps {
+    let mut props = ElementProps::new(AIR, 0.03, 0.0, 0.01, 0.95);
+    props.shade = || rand_color_grey(1.0, 0.05);
+    props.phase = Phase::Gas;
+    props
+}
 
-    Pixel::new(4, pos, [0.0; 2], [r, 0.0, 0.0, 1.0], 0.9, 0.2, 1.0, 0.95)
+pub fn sand() -> ElementProps {
+    let mut props = ElementProps::new(SAND, 0.8, 0.4, 1.2, 0.99);
+    props.shade = || [rand_color(0.8, 0.15), rand_color(0.8, 0.1), 0.0, 1.0];
+    props.phase = Phase::Powder;
+    props.stickiness = 0.3;
+    props
 }
 
-pub fn stone(pos: [u32; 2]) -> Pixel {
-    let cl = rand_color_grey(0.1, 0.25);
+pub fn water() -> ElementProps {
+    let mut props = ElementProps::new(WATER, 0.5, 0.0, 1.0, 0.99);
+    props.shade = || [0.0, 0.0, rand_color(0.8, 0.15), 1.0];
+    props.phase = Phase::Liquid;
+    props.dispersion = 5;
+    props
+}
 
-    Pixel::new(5, pos, [0.0; 2], cl, 0.94, 0.6, 1.0, 0.99)
+pub fn lava() -> ElementProps {
+    let mut props = ElementProps::new(LAVA, 0.9, 0.2, 1.0, 0.95);
+    props.shade = || [rand_color(0.8, 0.15), 0.0, 0.0, 1.0];
+    props.phase = Phase::Liquid;
+    props.dispersion = 2;
+    props.viscosity = 0.7;
+    //always, it never burns out
+    props.heat_output = 60;
+    props
 }
 
-pub fn brick(pos: [u32; 2]) -> Pixel {
-    let r = rand_color(0.8, 0.15);
-    let g = rand_color(0.4, 0.1);
-    let b = rand_color(0.2, 0.1);
+pub fn stone() -> ElementProps {
+    let mut props = ElementProps::new(STONE, 0.94, 0.6, 1.0, 0.99);
+    props.shade = || rand_color_grey(0.1, 0.25);
+    props
+}
 
-    Pixel::new(6, pos, [0.0; 2], [r, g, b, 1.0], 0.98, 1.1, 1.0, 0.99)
+pub fn brick() -> ElementProps {
+    let mut props = ElementProps::new(BRICK, 0.98, 1.1, 1.0, 0.99);
+    props.shade = || {
+        [
+            rand_color(0.8, 0.15),
+            rand_color(0.4, 0.1),
+            rand_color(0.2, 0.1),
+            1.0,
+        ]
+    };
+    props
 }
 
 //fuck ion kno
@@ -197,3 +416,31 @@ pub fn brick(pos: [u32; 2]) -> Pixel {
 //         false
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_match_their_names() {
+        let codes = [
+            (AIR, "air"),
+            (SAND, "sand"),
+            (WATER, "water"),
+            (LAVA, "lava"),
+            (STONE, "stone"),
+            (BRICK, "brick"),
+            (WOOD, "wood"),
+            (SMOKE, "smoke"),
+            (GLASS, "glass"),
+            (FIRE, "fire"),
+            (GUNPOWDER, "gunpowder"),
+            (EMITTER, "emitter"),
+            (SINK, "sink"),
+        ];
+        for (code, name) in codes {
+            assert_eq!(NAMES[code as usize], name);
+            assert_eq!(element(code).code, code);
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use rand::Rng;
+
+use std::cmp::Ordering;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::elements::ElementProps;
+use crate::grid::{Grid, Motion};
+use crate::random;
+
+//bits after the point, a cell is 1 << FRAC_BITS
+const FRAC_BITS: u32 = 16;
+const ONE: i64 = 1 << FRAC_BITS;
+
+//how the movement pass does its sums, Fixed keeps velocities in integers and
+//does them on integers so a seeded run comes out the same whatever the
+//compiler or the platform does with floats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Math {
+    Float,
+    Fixed,
+}
+
+impl Math {
+    pub fn name(self) -> &'static str {
+        match self {
+            Math::Float => "float",
+            Math::Fixed => "fixed",
+        }
+    }
+}
+
+//a fixed point number, 1/65536ths of a cell
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fx(pub i32);
+
+impl Add for Fx {
+    type Output = Fx;
+    fn add(self, other: Fx) -> Fx {
+        Fx(self.0 + other.0)
+    }
+}
+
+impl Sub for Fx {
+    type Output = Fx;
+    fn sub(self, other: Fx) -> Fx {
+        Fx(self.0 - other.0)
+    }
+}
+
+impl Mul for Fx {
+    type Output = Fx;
+    fn mul(self, other: Fx) -> Fx {
+        Fx(((self.0 as i64 * other.0 as i64) >> FRAC_BITS) as i32)
+    }
+}
+
+impl Div for Fx {
+    type Output = Fx;
+    fn div(self, other: Fx) -> Fx {
+        Fx((((self.0 as i64) << FRAC_BITS) / other.0 as i64) as i32)
+    }
+}
+
+impl Neg for Fx {
+    type Output = Fx;
+    fn neg(self) -> Fx {
+        Fx(-self.0)
+    }
+}
+
+//the numbers the movement pass works in, it's written once over this and
+//run with f64 or Fx depending on Simulation::math
+pub trait Num:
+    Copy
+    + PartialOrd
+    + Send
+    + 'static
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    const ZERO: Self;
+
+    //the nearest value this kind of maths can hold, for constants and
+    //anything coming from outside the pass
+    fn of(v: f64) -> Self;
+    fn to_f64(self) -> f64;
+    fn abs(self) -> Self;
+    //whole cells, towards zero
+    fn trunc(self) -> Self;
+    //true with chance self
+    fn chance(self) -> bool;
+    //an order for every value, nan too, so a broken velocity is left for
+    //validate to report instead of panicking the sort
+    fn total_cmp(self, other: Self) -> Ordering;
+
+    //an element's movement constants in this maths
+    fn movement(props: &ElementProps) -> Movement<Self>;
+    fn vel(grid: &Grid, i: usize) -> [Self; 2];
+    fn set_vel(grid: &mut Grid, i: usize, vel: [Self; 2]);
+    //the part of a cell travelled but not yet moved
+    fn carry(grid: &Grid, i: usize) -> [Self; 2];
+    fn set_carry(grid: &mut Grid, i: usize, sub: [Self; 2]);
+}
+
+impl Num for f64 {
+    const ZERO: f64 = 0.0;
+
+    fn of(v: f64) -> f64 {
+        v
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn abs(self) -> f64 {
+        f64::abs(self)
+    }
+
+    fn trunc(self) -> f64 {
+        f64::trunc(self)
+    }
+
+    fn chance(self) -> bool {
+        random::rng().gen_range(0.0..1.0) < self
+    }
+
+    fn total_cmp(self, other: f64) -> Ordering {
+        f64::total_cmp(&self, &other)
+    }
+
+    fn movement(props: &ElementProps) -> Movement<f64> {
+        Movement::of(props)
+    }
+
+    fn vel(grid: &Grid, i: usize) -> [f64; 2] {
+        match &grid.motion {
+            Motion::Float { vel, .. } => vel[i],
+            Motion::Fixed { .. } => unreachable!("float maths on a fixed point grid"),
+        }
+    }
+
+    fn set_vel(grid: &mut Grid, i: usize, v: [f64; 2]) {
+        match &mut grid.motion {
+            Motion::Float { vel, .. } => vel[i] = v,
+            Motion::Fixed { .. } => unreachable!("float maths on a fixed point grid"),
+        }
+    }
+
+    fn carry(grid: &Grid, i: usize) -> [f64; 2] {
+        match &grid.motion {
+            Motion::Float { sub, .. } => sub[i].map(|v| v as f64),
+            Motion::Fixed { .. } => unreachable!("float maths on a fixed point grid"),
+        }
+    }
+
+    fn set_carry(grid: &mut Grid, i: usize, v: [f64; 2]) {
+        match &mut grid.motion {
+            Motion::Float { sub, .. } => sub[i] = v.map(|v| v as f32),
+            Motion::Fixed { .. } => unreachable!("float maths on a fixed point grid"),
+        }
+    }
+}
+
+impl Num for Fx {
+    const ZERO: Fx = Fx(0);
+
+    fn of(v: f64) -> Fx {
+        //scaling by a power of two is exact, only the rounding picks a value
+        Fx((v * ONE as f64).round() as i32)
+    }
+
+    fn to_f64(self) -> f64 {
+        self.0 as f64 / ONE as f64
+    }
+
+    fn abs(self) -> Fx {
+        Fx(self.0.abs())
+    }
+
+    fn trunc(self) -> Fx {
+        Fx(self.0 / ONE as i32 * ONE as i32)
+    }
+
+    //draws a whole number instead of a float
+    fn chance(self) -> bool {
+        random::rng().gen_range(0..ONE as i32) < self.0
+    }
+
+    fn total_cmp(self, other: Fx) -> Ordering {
+        self.cmp(&other)
+    }
+
+    fn movement(props: &ElementProps) -> Movement<Fx> {
+        props.fixed
+    }
+
+    fn vel(grid: &Grid, i: usize) -> [Fx; 2] {
+        match &grid.motion {
+            Motion::Fixed { vel, .. } => vel[i],
+            Motion::Float { .. } => unreachable!("fixed point maths on a float grid"),
+        }
+    }
+
+    fn set_vel(grid: &mut Grid, i: usize, v: [Fx; 2]) {
+        match &mut grid.motion {
+            Motion::Fixed { vel, .. } => vel[i] = v,
+            Motion::Float { .. } => unreachable!("fixed point maths on a float grid"),
+        }
+    }
+
+    fn carry(grid: &Grid, i: usize) -> [Fx; 2] {
+        match &grid.motion {
+            Motion::Fixed { sub, .. } => sub[i],
+            Motion::Float { .. } => unreachable!("fixed point maths on a float grid"),
+        }
+    }
+
+    fn set_carry(grid: &mut Grid, i: usize, v: [Fx; 2]) {
+        match &mut grid.motion {
+            Motion::Fixed { sub, .. } => sub[i] = v,
+            Motion::Float { .. } => unreachable!("fixed point maths on a float grid"),
+        }
+    }
+}
+
+//the element constants movement uses, the fixed point ones are worked out
+//once with the element table
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Movement<N> {
+    pub density: N,
+    pub min_force: N,
+    pub gravity_multiplier: N,
+    pub friction_multiplier: N,
+    pub viscosity: N,
+    pub stickiness: N,
+}
+
+impl<N: Num> Movement<N> {
+    pub fn of(props: &ElementProps) -> Movement<N> {
+        Movement {
+            density: N::of(props.density),
+            min_force: N::of(props.min_force),
+            gravity_multiplier: N::of(props.gravity_multiplier),
+            friction_multiplier: N::of(props.friction_multiplier),
+            viscosity: N::of(props.viscosity),
+            stickiness: N::of(props.stickiness),
+        }
+    }
+}
+
+//the simulation's own movement constants, converted once a tick
+#[derive(Clone, Copy, Debug)]
+pub struct Constants<N> {
+    pub gravity: N,
+    pub friction: N,
+    //faster than this a pixel flies instead of following its rule
+    pub flung: N,
+    //cells of weight a liquid needs above the gas over it to push it up,
+    //and the drop a push needs to find at the other end of the body
+    pub lift: N,
+    pub drop: N,
+}
+
+impl<N: Num> Constants<N> {
+    pub fn of(gravity: f64, friction: f64, flung: f64) -> Constants<N> {
+        Constants {
+            gravity: N::of(gravity),
+            friction: N::of(friction),
+            flung: N::of(flung),
+            //pressure in a body goes up a whole cell of weight at a time,
+            //half a cell either side of it so rounding can't tip it
+            lift: N::of(2.5),
+            drop: N::of(1.5),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_sums_stay_on_the_grid() {
+        let third = Fx::of(1.0) / Fx::of(3.0);
+        assert_eq!(third, Fx(21845));
+        assert_eq!(third * Fx::of(3.0), Fx(65535));
+        assert_eq!(Fx::of(0.1) + Fx::of(0.2), Fx(6554 + 13107));
+        assert_eq!(Fx::of(-1.5) * Fx::of(0.99), Fx(-97322));
+        assert_eq!(Fx::of(-1.75).trunc(), Fx::of(-1.0));
+        assert_eq!(Fx::of(-1.75).abs(), Fx::of(1.75));
+        assert_eq!(Fx(21845).to_f64(), 21845.0 / 65536.0);
+    }
+}
@@ -0,0 +1,128 @@
+//golden scene tests, every scenes/<name>.txt is a save that is loaded, run for
+//TICKS seeded steps and saved again, the result has to match scenes/<name>.golden
+//exactly, element codes only since colors are never saved
+//after changing how things move on purpose, rewrite the goldens with
+//BLESS=1 cargo test golden
+//and check the diff of scenes/*.golden before committing it
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::random;
+use crate::simulate::*;
+
+pub const TICKS: u32 = 30;
+pub const SEED: u64 = 1;
+const BLESS_ENV: &str = "BLESS";
+
+//the scene saved after TICKS steps from SEED
+pub fn run_scene(path: &Path) -> Result<String, String> {
+    let mut sim = Simulation::new();
+    //before loading, spawning fire rolls how long it lasts
+    random::seed(SEED);
+    sim.load(path.to_str().unwrap())
+        .map_err(|err| format!("couldn't load {}: {}", path.display(), err))?;
+    sim.validate = true;
+    for _ in 0..TICKS {
+        sim.step();
+        if let Some(err) = sim.broken.take() {
+            return Err(format!("{}: {}", path.display(), err));
+        }
+    }
+    Ok(sim.save_text())
+}
+
+//where expected and actual first differ, lines counted from 1
+pub fn first_difference(expected: &str, actual: &str) -> Option<(usize, String, String)> {
+    let mut expected = expected.lines();
+    let mut actual = actual.lines();
+    for i in 1.. {
+        match (expected.next(), actual.next()) {
+            (None, None) => return None,
+            (e, a) if e == a => continue,
+            (e, a) => {
+                return Some((
+                    i,
+                    e.unwrap_or("<end>").to_string(),
+                    a.unwrap_or("<end>").to_string(),
+                ))
+            }
+        }
+    }
+    None
+}
+
+fn scenes() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn golden_scenes() {
+        let bless = std::env::var_os(BLESS_ENV).is_some();
+        let mut failures = vec![];
+        for path in scenes() {
+            let golden = path.with_extension("golden");
+            let actual = match run_scene(&path) {
+                Ok(actual) => actual,
+                Err(err) => {
+                    failures.push(err);
+                    continue;
+                }
+            };
+            if bless {
+                fs::write(&golden, &actual).unwrap();
+                continue;
+            }
+            match fs::read_to_string(&golden) {
+                Ok(expected) => {
+                    if let Some((line, e, a)) = first_difference(&expected, &actual) {
+                        failures.push(format!(
+                            "{} line {}\n  expected {}\n  got      {}",
+                            golden.display(),
+                            line,
+                            e,
+                            a
+                        ));
+                    }
+                }
+                Err(_) => failures.push(format!("no golden for {}", path.display())),
+            }
+        }
+        assert!(
+            failures.is_empty(),
+            "{}\nrerun with {}=1 if this is on purpose",
+            failures.join("\n"),
+            BLESS_ENV
+        );
+    }
+
+    #[test]
+    fn seeded_runs_repeat() {
+        let path = &scenes()[0];
+        assert_eq!(run_scene(path), run_scene(path));
+    }
+
+    #[test]
+    fn differences_are_found() {
+        assert_eq!(first_difference("a\nb\n", "a\nb\n"), None);
+        assert_eq!(
+            first_difference("a\nb\n", "a\nc\n"),
+            Some((2, "b".to_string(), "c".to_string()))
+        );
+        assert_eq!(
+            first_difference("a\n", "a\nb\n"),
+            Some((2, "<end>".to_string(), "b".to_string()))
+        );
+    }
+}
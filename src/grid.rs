@@ -0,0 +1,326 @@
+use rand::Rng;
+
+use std::sync::Arc;
+
+use crate::elements::*;
+use crate::fixed::{Fx, Math, Num};
+use crate::pixel::*;
+
+//velocity and the part of a cell travelled but not yet moved, kept in
+//whatever the movement pass does its sums in
+#[derive(Clone)]
+pub enum Motion {
+    //sub is never a whole cell so single precision is plenty
+    Float {
+        vel: Vec<[f64; 2]>,
+        sub: Vec<[f32; 2]>,
+    },
+    Fixed {
+        vel: Vec<[Fx; 2]>,
+        sub: Vec<[Fx; 2]>,
+    },
+}
+
+impl Motion {
+    pub fn new(math: Math, n: usize) -> Motion {
+        match math {
+            Math::Float => Motion::Float {
+                vel: vec![[0.0; 2]; n],
+                sub: vec![[0.0; 2]; n],
+            },
+            Math::Fixed => Motion::Fixed {
+                vel: vec![[Fx(0); 2]; n],
+                sub: vec![[Fx(0); 2]; n],
+            },
+        }
+    }
+
+    pub fn math(&self) -> Math {
+        match self {
+            Motion::Float { .. } => Math::Float,
+            Motion::Fixed { .. } => Math::Fixed,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Motion::Float { vel, .. } => vel.len(),
+            Motion::Fixed { vel, .. } => vel.len(),
+        }
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        match self {
+            Motion::Float { vel, sub } => {
+                vel.swap(a, b);
+                sub.swap(a, b);
+            }
+            Motion::Fixed { vel, sub } => {
+                vel.swap(a, b);
+                sub.swap(a, b);
+            }
+        }
+    }
+
+    //the `len` cells from `start` of other on the end of this one
+    fn extend_from(&mut self, other: &Motion, start: usize, len: usize) {
+        match (self, other) {
+            (Motion::Float { vel, sub }, Motion::Float { vel: v, sub: s }) => {
+                vel.extend_from_slice(&v[start..start + len]);
+                sub.extend_from_slice(&s[start..start + len]);
+            }
+            (Motion::Fixed { vel, sub }, Motion::Fixed { vel: v, sub: s }) => {
+                vel.extend_from_slice(&v[start..start + len]);
+                sub.extend_from_slice(&s[start..start + len]);
+            }
+            _ => unreachable!("chunks keep the maths of their grid"),
+        }
+    }
+
+    //the `len` cells from `from` of other over the ones from `to`
+    fn copy_from(&mut self, to: usize, other: &Motion, from: usize, len: usize) {
+        match (self, other) {
+            (Motion::Float { vel, sub }, Motion::Float { vel: v, sub: s }) => {
+                vel[to..to + len].copy_from_slice(&v[from..from + len]);
+                sub[to..to + len].copy_from_slice(&s[from..from + len]);
+            }
+            (Motion::Fixed { vel, sub }, Motion::Fixed { vel: v, sub: s }) => {
+                vel[to..to + len].copy_from_slice(&v[from..from + len]);
+                sub[to..to + len].copy_from_slice(&s[from..from + len]);
+            }
+            _ => unreachable!("chunks keep the maths of their grid"),
+        }
+    }
+}
+
+//the world, one array per thing a cell keeps track of, indexed y * width + x,
+//everything an element always has lives in the element table instead
+#[derive(Clone)]
+pub struct Grid {
+    pub size: [u32; 2],
+    //element code
+    pub ptype: Vec<u8>,
+    pub motion: Motion,
+    //worked out at the start of each tick
+    pub pressure: Vec<f32>,
+    //which of the element's colours it is drawn in
+    pub shade: Vec<u8>,
+    //ticks left to burn, 0 when not burning
+    pub life: Vec<u8>,
+    //given off by things burning nearby, lights fuel and fades each tick
+    pub heat: Vec<u8>,
+    props: Arc<[ElementProps]>,
+}
+
+impl Grid {
+    //all air
+    pub fn new(size: [u32; 2], props: Arc<[ElementProps]>) -> Grid {
+        let n = (size[0] * size[1]) as usize;
+        let mut grid = Grid {
+            size,
+            ptype: vec![0; n],
+            motion: Motion::new(Math::Float, n),
+            pressure: vec![0.0; n],
+            shade: vec![0; n],
+            life: vec![0; n],
+            heat: vec![0; n],
+            props,
+        };
+        for y in 0..size[1] {
+            for x in 0..size[0] {
+                grid.set([x, y], Pixel::spawn("air".to_string()));
+            }
+        }
+        grid
+    }
+
+    pub fn index(&self, pos: [u32; 2]) -> usize {
+        (pos[1] * self.size[0] + pos[0]) as usize
+    }
+
+    pub fn pos_of(&self, i: usize) -> [u32; 2] {
+        [i as u32 % self.size[0], i as u32 / self.size[0]]
+    }
+
+    pub fn props(&self, i: usize) -> &ElementProps {
+        &self.props[self.ptype[i] as usize]
+    }
+
+    pub fn props_at(&self, pos: [u32; 2]) -> &ElementProps {
+        self.props(self.index(pos))
+    }
+
+    //constants of the element with this code
+    pub fn element(&self, code: u8) -> &ElementProps {
+        &self.props[code as usize]
+    }
+
+    pub fn ptype_at(&self, pos: [u32; 2]) -> u8 {
+        self.ptype[self.index(pos)]
+    }
+
+    //element codes of row y
+    pub fn row(&self, y: u32) -> &[u8] {
+        let start = self.index([0, y]);
+        &self.ptype[start..start + self.size[0] as usize]
+    }
+
+    //put a freshly spawned pixel in the cell at pos
+    pub fn set(&mut self, pos: [u32; 2], pix: Pixel) {
+        let i = self.index(pos);
+        self.ptype[i] = pix.ptype;
+        match &mut self.motion {
+            Motion::Float { vel, sub } => {
+                vel[i] = [0.0; 2];
+                sub[i] = [0.0; 2];
+            }
+            Motion::Fixed { vel, sub } => {
+                vel[i] = [Fx(0); 2];
+                sub[i] = [Fx(0); 2];
+            }
+        }
+        self.pressure[i] = 0.0;
+        self.life[i] = pix.life;
+        self.heat[i] = 0;
+        //only for looks, doesn't touch the seeded generator
+        self.shade[i] = rand::thread_rng().gen_range(0..SHADES) as u8;
+    }
+
+    pub fn swap(&mut self, a: [u32; 2], b: [u32; 2]) {
+        let a = self.index(a);
+        let b = self.index(b);
+        self.ptype.swap(a, b);
+        self.motion.swap(a, b);
+        self.pressure.swap(a, b);
+        self.shade.swap(a, b);
+        self.life.swap(a, b);
+        self.heat.swap(a, b);
+    }
+
+    //velocity in cells a tick, whatever it's kept in
+    pub fn velocity(&self, i: usize) -> [f64; 2] {
+        match &self.motion {
+            Motion::Float { vel, .. } => vel[i],
+            Motion::Fixed { vel, .. } => vel[i].map(Fx::to_f64),
+        }
+    }
+
+    pub fn set_velocity(&mut self, i: usize, v: [f64; 2]) {
+        match &mut self.motion {
+            Motion::Float { vel, .. } => vel[i] = v,
+            Motion::Fixed { vel, .. } => vel[i] = v.map(Fx::of),
+        }
+    }
+
+    //the part of a cell travelled but not yet moved
+    pub fn carried(&self, i: usize) -> [f64; 2] {
+        match &self.motion {
+            Motion::Float { sub, .. } => sub[i].map(|v| v as f64),
+            Motion::Fixed { sub, .. } => sub[i].map(Fx::to_f64),
+        }
+    }
+
+    //keep velocities in the other kind of maths, to the nearest value it can hold
+    pub fn set_math(&mut self, math: Math) {
+        self.motion = match (&self.motion, math) {
+            (Motion::Float { vel, sub }, Math::Fixed) => Motion::Fixed {
+                vel: vel.iter().map(|v| v.map(Fx::of)).collect(),
+                sub: sub.iter().map(|s| s.map(|v| Fx::of(v as f64))).collect(),
+            },
+            (Motion::Fixed { vel, sub }, Math::Float) => Motion::Float {
+                vel: vel.iter().map(|v| v.map(Fx::to_f64)).collect(),
+                sub: sub.iter().map(|s| s.map(|v| v.to_f64() as f32)).collect(),
+            },
+            _ => return,
+        };
+    }
+
+    pub fn is_burning(&self, i: usize) -> bool {
+        self.burns(self.ptype[i], self.life[i])
+    }
+
+    //would a cell of this element with this much fuel left be alight
+    pub fn burns(&self, ptype: u8, life: u8) -> bool {
+        ptype == FIRE || (life > 0 && self.element(ptype).flammability > 0.0)
+    }
+
+    //set a flammable pixel alight, returns false if it can't burn or already is
+    pub fn ignite(&mut self, i: usize) -> bool {
+        let props = self.props(i);
+        if props.flammability <= 0.0 || self.life[i] > 0 {
+            return false;
+        }
+        self.life[i] = props.burn_time;
+        true
+    }
+
+    pub fn color(&self, i: usize, elements: &ElementList) -> [f32; 4] {
+        elements.color(self.ptype[i], self.shade[i], self.is_burning(i))
+    }
+
+    //a copy of the `size` cells from `origin`, sharing the element table
+    pub fn chunk(&self, origin: [u32; 2], size: [u32; 2]) -> Grid {
+        let n = (size[0] * size[1]) as usize;
+        let mut chunk = Grid {
+            size,
+            ptype: Vec::with_capacity(n),
+            motion: Motion::new(self.motion.math(), 0),
+            pressure: Vec::with_capacity(n),
+            shade: Vec::with_capacity(n),
+            life: Vec::with_capacity(n),
+            heat: Vec::with_capacity(n),
+            props: self.props.clone(),
+        };
+        for y in origin[1]..origin[1] + size[1] {
+            let start = self.index([origin[0], y]);
+            let end = start + size[0] as usize;
+            chunk.ptype.extend_from_slice(&self.ptype[start..end]);
+            chunk.motion.extend_from(&self.motion, start, end - start);
+            chunk.pressure.extend_from_slice(&self.pressure[start..end]);
+            chunk.shade.extend_from_slice(&self.shade[start..end]);
+            chunk.life.extend_from_slice(&self.life[start..end]);
+            chunk.heat.extend_from_slice(&self.heat[start..end]);
+        }
+        chunk
+    }
+
+    //copy a chunk back in at `origin`
+    pub fn put_chunk(&mut self, origin: [u32; 2], chunk: &Grid) {
+        let w = chunk.size[0] as usize;
+        for y in 0..chunk.size[1] {
+            let from = chunk.index([0, y]);
+            let to = self.index([origin[0], origin[1] + y]);
+            self.ptype[to..to + w].copy_from_slice(&chunk.ptype[from..from + w]);
+            self.motion.copy_from(to, &chunk.motion, from, w);
+            self.pressure[to..to + w].copy_from_slice(&chunk.pressure[from..from + w]);
+            self.shade[to..to + w].copy_from_slice(&chunk.shade[from..from + w]);
+            self.life[to..to + w].copy_from_slice(&chunk.life[from..from + w]);
+            self.heat[to..to + w].copy_from_slice(&chunk.heat[from..from + w]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_go_back_where_they_came_from() {
+        let elements = ElementList::new();
+        let mut grid = Grid::new([6, 4], elements.props.clone());
+        grid.set([4, 1], Pixel::spawn("sand".to_string()));
+        let i = grid.index([5, 2]);
+        grid.set_velocity(i, [1.0, -2.0]);
+
+        let mut chunk = grid.chunk([3, 1], [3, 2]);
+        assert_eq!(chunk.ptype_at([1, 0]), 2);
+        assert_eq!(chunk.velocity(chunk.index([2, 1])), [1.0, -2.0]);
+
+        chunk.swap([1, 0], [0, 1]);
+        grid.put_chunk([3, 1], &chunk);
+        assert_eq!(grid.ptype_at([3, 2]), 2);
+        assert_eq!(grid.ptype_at([4, 1]), 1);
+        assert_eq!(grid.ptype.iter().filter(|ptype| **ptype == 2).count(), 1);
+        assert_eq!(grid.pos_of(grid.index([3, 2])), [3, 2]);
+    }
+}
@@ -0,0 +1,143 @@
+use log::{error, info};
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::fixed::Math;
+use crate::random;
+use crate::simulate::*;
+use crate::stats::*;
+
+pub const USAGE: &str =
+    "usage: ar --headless <ticks> [--load <save>] [--csv <path>] [--validate] [--seed <n>] [--fixed] [--log <levels>]
+levels are comma separated, e.g. info,simulate=trace, and can also come from AR_LOG";
+
+//settings for a run without a window
+pub struct Headless {
+    pub ticks: u64,
+    pub load: Option<String>,
+    pub csv: Option<String>,
+    //check invariants every tick and stop at the first one broken
+    pub validate: bool,
+    //the same seed and save always give the same run
+    pub seed: Option<u64>,
+    //fixed point movement, the same run on every machine
+    pub fixed: bool,
+}
+
+impl Headless {
+    pub fn parse(args: &[String]) -> Result<Headless, String> {
+        let mut headless = Headless {
+            ticks: 0,
+            load: None,
+            csv: None,
+            validate: false,
+            seed: None,
+            fixed: false,
+        };
+        let mut ticks = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", arg))
+            };
+            match arg.as_str() {
+                "--headless" => {
+                    ticks = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "ticks must be a number".to_string())?,
+                    );
+                }
+                "--load" => headless.load = Some(value()?),
+                "--csv" => headless.csv = Some(value()?),
+                "--validate" => headless.validate = true,
+                "--fixed" => headless.fixed = true,
+                "--seed" => {
+                    headless.seed = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "seed must be a number".to_string())?,
+                    );
+                }
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+        //anything else on its own would run nothing and say nothing
+        headless.ticks = ticks.ok_or_else(|| "--headless <ticks> is required".to_string())?;
+        Ok(headless)
+    }
+
+    pub fn run(&self) -> Result<(), String> {
+        if let Some(seed) = self.seed {
+            random::seed(seed);
+        }
+        let mut sim = Simulation::new();
+        sim.validate = self.validate;
+        if self.fixed {
+            sim.math = Math::Fixed;
+        }
+        if let Some(path) = &self.load {
+            sim.load(path)
+                .map_err(|err| format!("couldn't load {}: {}", path, err))?;
+        }
+        info!(
+            "running {} ticks on a {}x{} grid with {} maths",
+            self.ticks,
+            sim.size[0],
+            sim.size[1],
+            sim.math.name()
+        );
+
+        let mut csv = match &self.csv {
+            Some(path) => {
+                let file = File::create(path)
+                    .map_err(|err| format!("couldn't create {}: {}", path, err))?;
+                let mut out = BufWriter::new(file);
+                writeln!(out, "{}", Stats::csv_header(&sim.elements)).map_err(|e| e.to_string())?;
+                Some(out)
+            }
+            None => None,
+        };
+
+        for _ in 0..self.ticks {
+            sim.step();
+            if let Some(err) = sim.broken.take() {
+                return Err(err);
+            }
+            //stdout is kept for the summary
+            if sim.stats.empty > 0 {
+                error!(
+                    "{} type 0 pixels at tick {}",
+                    sim.stats.empty, sim.stats.tick
+                );
+            }
+            if let Some(out) = &mut csv {
+                writeln!(out, "{}", sim.stats.csv_row()).map_err(|e| e.to_string())?;
+            }
+        }
+
+        println!("{}", Stats::csv_header(&sim.elements));
+        println!("{}", sim.stats.csv_row());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn needs_a_tick_count() {
+        assert!(Headless::parse(&args("--seed 3")).is_err());
+        let headless = Headless::parse(&args("--seed 3 --headless 10")).unwrap();
+        assert_eq!(headless.ticks, 10);
+        assert_eq!(headless.seed, Some(3));
+    }
+}
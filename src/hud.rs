@@ -0,0 +1,188 @@
+use piston_window::*;
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::simulate::*;
+use crate::timing::*;
+
+//built in so the binary runs from anywhere, see assets/DejaVuSansMono-LICENSE.txt
+const FONT: &[u8] = include_bytes!("../assets/DejaVuSansMono.ttf");
+const FONT_SIZE: u32 = 12;
+const LINE_HEIGHT: f64 = 16.0;
+const PADDING: f64 = 6.0;
+
+//how many times something happened in the last second
+pub struct RateCounter {
+    times: VecDeque<Instant>,
+}
+
+impl RateCounter {
+    pub fn new() -> RateCounter {
+        RateCounter {
+            times: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, now: Instant) {
+        self.times.push_back(now);
+        while let Some(first) = self.times.front() {
+            if now.duration_since(*first) < Duration::from_secs(1) {
+                break;
+            }
+            self.times.pop_front();
+        }
+    }
+
+    pub fn rate(&self, now: Instant) -> usize {
+        self.times
+            .iter()
+            .filter(|time| now.duration_since(**time) < Duration::from_secs(1))
+            .count()
+    }
+}
+
+//text in the top left corner about the simulation and what the mouse is doing
+pub struct Hud {
+    pub visible: bool,
+    //render events
+    pub frames: RateCounter,
+    //simulation steps
+    pub ticks: RateCounter,
+    text: Vec<String>,
+    glyphs: Glyphs,
+}
+
+impl Hud {
+    pub fn new(window: &mut PistonWindow) -> Hud {
+        let glyphs = Glyphs::from_bytes(
+            FONT,
+            window.create_texture_context(),
+            TextureSettings::new(),
+        )
+        .unwrap();
+        Hud {
+            visible: true,
+            frames: RateCounter::new(),
+            ticks: RateCounter::new(),
+            text: vec![],
+            glyphs,
+        }
+    }
+
+    pub fn update(&mut self, sim: &Simulation, time: &TimeScale, material: &str, brush: u32) {
+        let now = Instant::now();
+        self.text = hud_lines(
+            sim,
+            time,
+            self.frames.rate(now),
+            self.ticks.rate(now),
+            material,
+            brush,
+        );
+    }
+
+    pub fn draw(&mut self, context: Context, graphics: &mut G2d, device: &mut GfxDevice) {
+        if !self.visible {
+            return;
+        }
+        let longest = self.text.iter().map(|line| line.len()).max().unwrap_or(0);
+        //monospace, every glyph is the same width
+        let width = longest as f64 * FONT_SIZE as f64 * 0.6 + PADDING * 2.0;
+        let height = self.text.len() as f64 * LINE_HEIGHT + PADDING * 2.0;
+        rectangle(
+            [1.0, 1.0, 1.0, 0.75],
+            [0.0, 0.0, width, height],
+            context.transform,
+            graphics,
+        );
+
+        let text = text::Text::new_color([0.0, 0.0, 0.0, 1.0], FONT_SIZE);
+        for (i, line) in self.text.iter().enumerate() {
+            let y = PADDING + (i + 1) as f64 * LINE_HEIGHT - 4.0;
+            text.draw(
+                line,
+                &mut self.glyphs,
+                &context.draw_state,
+                context.transform.trans(PADDING, y),
+                graphics,
+            )
+            .unwrap();
+        }
+        //glyphs are uploaded on their own encoder
+        self.glyphs.factory.encoder.flush(device);
+    }
+}
+
+pub fn hud_lines(
+    sim: &Simulation,
+    time: &TimeScale,
+    fps: usize,
+    tps: usize,
+    material: &str,
+    brush: u32,
+) -> Vec<String> {
+    let pos = sim.mouse_pos;
+    let i = sim.grid.index(pos);
+    let vel = sim.grid.velocity(i);
+    vec![
+        format!("fps {}  tps {}", fps, tps),
+        format!(
+            "speed {}x{}",
+            time.speed(),
+            if time.paused { "  paused" } else { "" }
+        ),
+        format!(
+            "{} at {} {}  vel {:.2} {:.2}  density {:.2}",
+            sim.elements.get_name(sim.grid.ptype[i]),
+            pos[0],
+            pos[1],
+            vel[0],
+            vel[1],
+            sim.grid.props(i).density
+        ),
+        format!("material {}  brush {}", material, brush),
+        format!(
+            "edges {} {}",
+            sim.boundary[0].name(),
+            sim.boundary[1].name()
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_counts_the_last_second() {
+        let start = Instant::now();
+        let mut counter = RateCounter::new();
+        for i in 0..30 {
+            counter.record(start + Duration::from_millis(i * 50));
+        }
+        //1.45s in, the first ten are over a second old
+        assert_eq!(counter.rate(start + Duration::from_millis(1450)), 20);
+        assert_eq!(counter.rate(start + Duration::from_secs(10)), 0);
+    }
+
+    #[test]
+    fn lines_describe_the_cell_under_the_mouse() {
+        let mut sim = Simulation::new();
+        sim.set_pixel([4, 7], "sand".to_string());
+        let i = sim.grid.index([4, 7]);
+        sim.grid.set_velocity(i, [0.5, 2.0]);
+        sim.mouse_pos = [4, 7];
+
+        let mut time = TimeScale::new();
+        time.slower();
+        time.toggle_pause();
+
+        let lines = hud_lines(&sim, &time, 60, 30, "water", 5);
+        assert_eq!(lines[0], "fps 60  tps 30");
+        assert_eq!(lines[1], "speed 0.5x  paused");
+        assert_eq!(lines[2], "sand at 4 7  vel 0.50 2.00  density 0.80");
+        assert_eq!(lines[3], "material water  brush 5");
+        assert_eq!(lines[4], "edges wall wall");
+    }
+}
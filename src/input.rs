@@ -0,0 +1,233 @@
+use piston_window::*;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+
+use crate::elements::*;
+
+//used when there is no bindings file next to the binary
+const DEFAULT_BINDINGS: &str = include_str!("../keys.txt");
+
+//something the player can do, bound to a key or mouse button
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    //held, happen every event while the button is down
+    PlaceMaterial(String),
+    //a whole row at the mouse height
+    PlaceRow(String),
+    //from where the button went down to where it comes up
+    DrawLine(String),
+    Erase,
+    Blast,
+    DragView,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    //pressed, happen once
+    NextEmitterElement,
+    Save,
+    Load,
+    CycleEdgeX,
+    CycleEdgeY,
+    ToggleOutlines,
+    ToggleChunks,
+    ToggleVelocity,
+    CycleHeatmap,
+    ToggleReactions,
+    ToggleHud,
+    ToggleValidate,
+    Pause,
+    Step,
+    Slower,
+    Faster,
+    BrushSmaller,
+    BrushBigger,
+}
+
+impl Action {
+    pub fn parse(words: &[&str], elements: &ElementList) -> Result<Action, String> {
+        let element = |name: &str| {
+            let code = elements._get(name.to_string());
+            if code == 0 {
+                Err(format!("unknown element {}", name))
+            } else {
+                Ok(name.to_string())
+            }
+        };
+        let action = match words {
+            ["place", name] => Action::PlaceMaterial(element(name)?),
+            ["place_row", name] => Action::PlaceRow(element(name)?),
+            ["draw_line", name] => Action::DrawLine(element(name)?),
+            ["erase"] => Action::Erase,
+            ["blast"] => Action::Blast,
+            ["drag_view"] => Action::DragView,
+            ["pan_up"] => Action::PanUp,
+            ["pan_down"] => Action::PanDown,
+            ["pan_left"] => Action::PanLeft,
+            ["pan_right"] => Action::PanRight,
+            ["next_emitter"] => Action::NextEmitterElement,
+            ["save"] => Action::Save,
+            ["load"] => Action::Load,
+            ["cycle_edge_x"] => Action::CycleEdgeX,
+            ["cycle_edge_y"] => Action::CycleEdgeY,
+            ["toggle_outlines"] => Action::ToggleOutlines,
+            ["toggle_chunks"] => Action::ToggleChunks,
+            ["toggle_velocity"] => Action::ToggleVelocity,
+            ["cycle_heatmap"] => Action::CycleHeatmap,
+            ["toggle_reactions"] => Action::ToggleReactions,
+            ["toggle_hud"] => Action::ToggleHud,
+            ["toggle_validate"] => Action::ToggleValidate,
+            ["pause"] => Action::Pause,
+            ["step"] => Action::Step,
+            ["slower"] => Action::Slower,
+            ["faster"] => Action::Faster,
+            ["brush_smaller"] => Action::BrushSmaller,
+            ["brush_bigger"] => Action::BrushBigger,
+            _ => return Err(format!("unknown action {}", words.join(" "))),
+        };
+        Ok(action)
+    }
+}
+
+//mouse_left, mouse_right, mouse_middle or a key by its name, any case
+pub fn parse_button(name: &str) -> Option<Button> {
+    match name {
+        "mouse_left" => return Some(Button::Mouse(MouseButton::Left)),
+        "mouse_right" => return Some(Button::Mouse(MouseButton::Right)),
+        "mouse_middle" => return Some(Button::Mouse(MouseButton::Middle)),
+        _ => {}
+    }
+    //every key code piston knows about
+    let codes = (0..0x80).chain(0x4000_0039..0x4000_0120);
+    codes
+        .map(Key::from)
+        .find(|key| *key != Key::Unknown && format!("{:?}", key).eq_ignore_ascii_case(name))
+        .map(Button::Keyboard)
+}
+
+//what each button does and which ones are down
+pub struct InputMap {
+    bindings: HashMap<Button, Action>,
+    held: Vec<Button>,
+}
+
+impl InputMap {
+    pub fn parse(text: &str, elements: &ElementList) -> Result<InputMap, String> {
+        let mut bindings = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            let button = parse_button(words[0])
+                .ok_or_else(|| format!("line {}: unknown button {}", i + 1, words[0]))?;
+            let action = Action::parse(&words[1..], elements)
+                .map_err(|err| format!("line {}: {}", i + 1, err))?;
+            bindings.insert(button, action);
+        }
+        Ok(InputMap {
+            bindings,
+            held: vec![],
+        })
+    }
+
+    //bindings from `path`, or the defaults if there is no such file
+    pub fn load(path: &str, elements: &ElementList) -> Result<InputMap, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => InputMap::parse(&text, elements),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                InputMap::parse(DEFAULT_BINDINGS, elements)
+            }
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    pub fn defaults(elements: &ElementList) -> InputMap {
+        InputMap::parse(DEFAULT_BINDINGS, elements).unwrap()
+    }
+
+    //the action bound to a button that just went down
+    pub fn press(&mut self, button: Button) -> Option<Action> {
+        let action = self.bindings.get(&button)?.clone();
+        if !self.held.contains(&button) {
+            self.held.push(button);
+        }
+        Some(action)
+    }
+
+    //the action bound to a button that just came up
+    pub fn release(&mut self, button: Button) -> Option<Action> {
+        self.held.retain(|held| *held != button);
+        self.bindings.get(&button).cloned()
+    }
+
+    //actions whose buttons are down, in the order they were pressed
+    pub fn held(&self) -> Vec<Action> {
+        self.held
+            .iter()
+            .filter_map(|button| self.bindings.get(button).cloned())
+            .collect()
+    }
+
+    pub fn is_held(&self, action: &Action) -> bool {
+        self.held
+            .iter()
+            .any(|button| self.bindings.get(button) == Some(action))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_parse() {
+        let input = InputMap::defaults(&ElementList::new());
+        assert_eq!(
+            input.bindings.get(&Button::Keyboard(Key::LShift)),
+            Some(&Action::PlaceMaterial("brick".to_string()))
+        );
+        assert_eq!(
+            input.bindings.get(&Button::Mouse(MouseButton::Left)),
+            Some(&Action::DrawLine("stone".to_string()))
+        );
+        assert_eq!(
+            input.bindings.get(&Button::Keyboard(Key::LeftBracket)),
+            Some(&Action::Slower)
+        );
+    }
+
+    #[test]
+    fn held_follows_presses() {
+        let elements = ElementList::new();
+        let mut input = InputMap::parse("a erase\nB erase\nf5 save\n", &elements).unwrap();
+        let a = Button::Keyboard(Key::A);
+        let b = Button::Keyboard(Key::B);
+
+        assert_eq!(input.press(a), Some(Action::Erase));
+        assert_eq!(input.press(b), Some(Action::Erase));
+        assert_eq!(input.release(a), Some(Action::Erase));
+        //still down on the other key
+        assert!(input.is_held(&Action::Erase));
+        input.release(b);
+        assert!(input.held().is_empty());
+
+        assert_eq!(input.press(Button::Keyboard(Key::Z)), None);
+        assert!(input.held().is_empty());
+    }
+
+    #[test]
+    fn bad_bindings_say_where() {
+        let elements = ElementList::new();
+        let err = |text: &str| InputMap::parse(text, &elements).err().unwrap();
+        assert_eq!(err("a erase\nnokey erase"), "line 2: unknown button nokey");
+        assert_eq!(err("a dance"), "line 1: unknown action dance");
+        assert_eq!(
+            err("# fine\na place cheese"),
+            "line 2: unknown element cheese"
+        );
+    }
+}
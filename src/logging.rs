@@ -0,0 +1,143 @@
+use log::{LevelFilter, Log, Metadata, Record};
+
+//log levels come from here or the --log flag, e.g. AR_LOG=info,simulate=trace
+pub const LOG_ENV: &str = "AR_LOG";
+
+//writes to stderr, levels set per module, nothing is logged unless asked for
+pub struct Logger {
+    default: LevelFilter,
+    //module path and level, the longest matching path wins
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Logger {
+    //comma separated `level` or `module=level`, modules with or without the crate name
+    pub fn parse(spec: &str) -> Result<Logger, String> {
+        let mut logger = Logger {
+            default: LevelFilter::Off,
+            modules: vec![],
+        };
+        for part in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            match part.split_once('=') {
+                Some((module, level)) => {
+                    let level = parse_level(level)?;
+                    let module = if module.starts_with(env!("CARGO_PKG_NAME")) {
+                        module.to_string()
+                    } else {
+                        format!("{}::{}", env!("CARGO_PKG_NAME"), module)
+                    };
+                    logger.modules.push((module, level));
+                }
+                None => logger.default = parse_level(part)?,
+            }
+        }
+        //longest first so the most specific module is found first
+        logger
+            .modules
+            .sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+        Ok(logger)
+    }
+
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        for (module, level) in &self.modules {
+            if target == module || target.starts_with(&format!("{}::", module)) {
+                return *level;
+            }
+        }
+        self.default
+    }
+
+    //the most verbose level anything is set to
+    pub fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level
+        .parse()
+        .map_err(|_| format!("unknown log level {}", level))
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!(
+                "{:5} {}: {}",
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+//pull `--log <spec>` out of the arguments
+pub fn take_log_flag(args: &mut Vec<String>) -> Result<Option<String>, String> {
+    let Some(i) = args.iter().position(|arg| arg == "--log") else {
+        return Ok(None);
+    };
+    if i + 1 >= args.len() {
+        return Err("--log needs a value".to_string());
+    }
+    let spec = args.remove(i + 1);
+    args.remove(i);
+    Ok(Some(spec))
+}
+
+//start logging from the flag if there was one, otherwise the environment
+pub fn init(flag: Option<String>) -> Result<(), String> {
+    let spec = flag
+        .or_else(|| std::env::var(LOG_ENV).ok())
+        .unwrap_or_default();
+    let logger = Logger::parse(&spec)?;
+    log::set_max_level(logger.max_level());
+    log::set_logger(Box::leak(Box::new(logger))).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_per_module() {
+        let logger = Logger::parse("warn, simulate=trace, ar::save=off").unwrap();
+        assert_eq!(logger.level_for("ar::simulate"), LevelFilter::Trace);
+        assert_eq!(logger.level_for("ar::save"), LevelFilter::Off);
+        assert_eq!(logger.level_for("ar::headless"), LevelFilter::Warn);
+        //a module that only starts with the same name
+        assert_eq!(logger.level_for("ar::simulated"), LevelFilter::Warn);
+        assert_eq!(logger.max_level(), LevelFilter::Trace);
+
+        let silent = Logger::parse("").unwrap();
+        assert_eq!(silent.level_for("ar::simulate"), LevelFilter::Off);
+        assert!(Logger::parse("simulate=loud").is_err());
+    }
+
+    #[test]
+    fn log_flag_is_taken_out() {
+        let mut args: Vec<String> = ["--headless", "10", "--log", "debug"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert_eq!(take_log_flag(&mut args), Ok(Some("debug".to_string())));
+        assert_eq!(args, vec!["--headless", "10"]);
+        assert_eq!(take_log_flag(&mut args), Ok(None));
+
+        let mut args = vec!["--log".to_string()];
+        assert!(take_log_flag(&mut args).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use log::{error, info, log_enabled, warn, Level};
 use piston_window::*;
 //use rand::Rng;
 
@@ -7,34 +8,131 @@ mod pixel;
 mod simulate;
 use simulate::*;
 
+mod camera;
+use camera::*;
+
 mod draw;
 use draw::*;
 
+mod overlay;
+use overlay::*;
+
+mod bench;
 mod elements;
+mod fixed;
+#[cfg(test)]
+mod golden;
+mod grid;
+mod headless;
+mod hud;
+use hud::*;
+mod input;
+use input::*;
+mod logging;
+mod random;
+
+mod save;
+mod stats;
+mod timing;
+use timing::*;
+mod validate;
 use std::io::{stdin, stdout, Read, Write};
-use std::thread;
+
+const SAVE_PATH: &str = "save.txt";
+const KEYS_PATH: &str = "keys.txt";
+//screen pixels the view moves per update while a pan key is held
+const PAN_SPEED: f64 = 8.0;
+//zoom change per scroll notch
+const ZOOM_STEP: f64 = 1.25;
+//update events per second, the time scale decides how many ticks each one runs
+const UPS: u64 = 120;
+const MAX_FPS: u64 = 60;
 
 fn _pause() {
     let mut stdout = stdout();
-    stdout.write(b"Press Enter to continue...").unwrap();
+    stdout.write_all(b"Press Enter to continue...").unwrap();
     stdout.flush().unwrap();
-    stdin().read(&mut [0]).unwrap();
+    stdin().read_exact(&mut [0]).unwrap();
 }
 
 fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let started = logging::take_log_flag(&mut args).and_then(logging::init);
+    if let Err(err) = started {
+        println!("{}\n{}", err, headless::USAGE);
+        std::process::exit(1);
+    }
+    if args.first().map(String::as_str) == Some("--bench") {
+        let result = bench::Bench::parse(&args).and_then(|bench| bench.run());
+        if let Err(err) = result {
+            println!("{}\n{}", err, bench::USAGE);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if !args.is_empty() {
+        let result = headless::Headless::parse(&args).and_then(|headless| headless.run());
+        if let Err(err) = result {
+            println!("{}\n{}\n{}", err, headless::USAGE, bench::USAGE);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let mut sim = Simulation::new();
 
-    let mut left_click = false;
-    let mut last_left_click = [0, 0];
-    let mut right_click = false;
-    let mut middle_click = false;
-    let mut space = false;
-    let mut shift = false;
-    let mut ctrl = false;
-    let mut alt = false;
-    let mut _tab = false;
-
-    while let Some(event) = sim.window.next() {
+    let mut window: PistonWindow = WindowSettings::new(
+        "Pixel Simulation",
+        [sim.size[0] * sim.scale, sim.size[1] * sim.scale],
+    )
+    .exit_on_esc(true)
+    .build()
+    .unwrap();
+    window.set_ups(UPS);
+    window.set_max_fps(MAX_FPS);
+    info!(
+        "window {}x{}, grid {}x{}",
+        sim.size[0] * sim.scale,
+        sim.size[1] * sim.scale,
+        sim.size[0],
+        sim.size[1]
+    );
+    let mut camera = Camera::new(
+        [
+            (sim.size[0] * sim.scale) as f64,
+            (sim.size[1] * sim.scale) as f64,
+        ],
+        sim.scale as f64,
+    );
+    //screen position of the mouse
+    let mut cursor = [0.0; 2];
+    let mut grid_texture =
+        GridTexture::new(&mut window, sim.size, grid_rgba(&sim.grid, &sim.elements));
+    let mut heatmap =
+        GridTexture::new(&mut window, sim.size, heatmap_rgba(&sim.grid, Heatmap::Off));
+    let mut overlays = Overlays::new();
+    let mut hud = Hud::new(&mut window);
+    //what was placed last
+    let mut material = "stone".to_string();
+    //erase radius
+    let mut brush: u32 = 5;
+    let mut time = TimeScale::new();
+
+    //keys.txt to remap controls
+    let mut input = match InputMap::load(KEYS_PATH, &sim.elements) {
+        Ok(input) => input,
+        Err(err) => {
+            warn!(
+                "couldn't load {}, using the default keys: {}",
+                KEYS_PATH, err
+            );
+            InputMap::defaults(&sim.elements)
+        }
+    };
+    //where the line being drawn started
+    let mut line_start = [0, 0];
+
+    while let Some(event) = window.next() {
         //draw on render
         if let Some(_args) = event.render_args() {
             //prepare cursor arguments,
@@ -43,20 +141,21 @@ fn main() {
             let mut pos = [
                 sim.mouse_pos[0],
                 sim.mouse_pos[1],
-                last_left_click[0],
-                last_left_click[1],
+                line_start[0],
+                line_start[1],
             ];
+            let held = input.held();
 
-            if left_click {
+            if held.iter().any(|a| matches!(a, Action::DrawLine(_))) {
                 //from last click to mouse pos
                 case = 1;
-            } else if right_click {
+            } else if held.contains(&Action::Erase) {
                 //radius
                 case = 2;
 
-                pos[2] = 5;
-                pos[3] = 5;
-            } else if space {
+                pos[2] = brush;
+                pos[3] = brush;
+            } else if held.iter().any(|a| matches!(a, Action::PlaceRow(_))) {
                 //line across screen
                 case = 3;
             } else {
@@ -64,158 +163,153 @@ fn main() {
                 case = 4;
             }
 
+            hud.frames.record(std::time::Instant::now());
+            hud.update(&sim, &time, &material, brush);
             new_frame(
-                &mut sim.window,
+                &mut window,
                 &event,
-                &sim.grid,
-                sim.scale,
-                case,
-                pos,
-                sim.size,
+                &sim,
+                &mut grid_texture,
+                &camera,
+                Layers {
+                    overlays: &overlays,
+                    case,
+                    pos,
+                    hud: &mut hud,
+                    heatmap: &mut heatmap,
+                },
             );
         }
 
+        if let Some(args) = event.resize_args() {
+            camera.window = args.window_size;
+        }
+
         //track mouse position
         if let Some(pos) = event.mouse_cursor_args() {
-            //integer and scaled mouse position
-            let x = pos[0] / sim.scale as f64;
-            let y = pos[1] / sim.scale as f64;
-            //limit mouse position to grid
-            if 0.0 <= x && x < sim.size[0] as f64 && 0.0 <= y && y < sim.size[1] as f64 {
-                sim.mouse_pos = [x as u32, y as u32];
-                //println!("MOUSE {} {}", x, y);
+            if input.is_held(&Action::DragView) {
+                camera.pan([pos[0] - cursor[0], pos[1] - cursor[1]]);
             }
+            cursor = pos;
         }
-
-        //track button holds
-        //m down
-        if let Some(Button::Mouse(button)) = event.press_args() {
-            if button == MouseButton::Left {
-                left_click = true;
-                //left click to place pixel
-                last_left_click = sim.mouse_pos;
-            }
-            if button == MouseButton::Right {
-                right_click = true;
-            }
-            if button == MouseButton::Middle {
-                middle_click = true;
-            }
+        //scroll to zoom in and out around the mouse
+        if let Some(scroll) = event.mouse_scroll_args() {
+            camera.zoom_at(cursor, ZOOM_STEP.powf(scroll[1]));
         }
-        //m up
-        if let Some(Button::Mouse(button)) = event.release_args() {
-            if button == MouseButton::Left {
-                left_click = false;
-                //left click to place pixel
-                sim.place_line(last_left_click, "stone".to_string());
-            }
-            if button == MouseButton::Right {
-                right_click = false;
-            }
-            if button == MouseButton::Middle {
-                middle_click = false;
-            }
+        //the view can move under a still mouse, so always go through the camera
+        //limit mouse position to grid
+        if let Some(cell) = camera.cell_at(cursor, sim.size) {
+            sim.mouse_pos = cell;
         }
 
-        //k down
-        if let Some(Button::Keyboard(key)) = event.press_args() {
-            if key == Key::Space {
-                space = true;
-            }
-            if key == Key::LShift {
-                shift = true;
-            }
-            if key == Key::LCtrl {
-                ctrl = true;
-            }
-            if key == Key::LAlt {
-                alt = true;
-            }
-            if key == Key::Tab {
-                _tab = true;
+        //buttons going down
+        if let Some(action) = event.press_args().and_then(|button| input.press(button)) {
+            match action {
+                Action::DrawLine(_) => line_start = sim.mouse_pos,
+                Action::NextEmitterElement => {
+                    info!("emitter {}", sim.next_emitter_element());
+                }
+                //saving and loading log what they did themselves
+                Action::Save => {
+                    if let Err(err) = sim.save(SAVE_PATH) {
+                        warn!("couldn't save {}: {}", SAVE_PATH, err);
+                    }
+                }
+                Action::Load => {
+                    if let Err(err) = sim.load(SAVE_PATH) {
+                        warn!("couldn't load {}: {}", SAVE_PATH, err);
+                    }
+                }
+                Action::CycleEdgeX | Action::CycleEdgeY => {
+                    let axis = (action == Action::CycleEdgeY) as usize;
+                    sim.boundary[axis] = sim.boundary[axis].next();
+                    info!(
+                        "edges {} {}",
+                        sim.boundary[0].name(),
+                        sim.boundary[1].name()
+                    );
+                }
+                Action::ToggleOutlines => overlays.outlines = !overlays.outlines,
+                Action::ToggleChunks => overlays.chunks = !overlays.chunks,
+                Action::ToggleVelocity => overlays.velocity = !overlays.velocity,
+                Action::CycleHeatmap => overlays.heatmap = overlays.heatmap.next(),
+                Action::ToggleReactions => overlays.reactions = !overlays.reactions,
+                Action::ToggleHud => hud.visible = !hud.visible,
+                Action::ToggleValidate => {
+                    sim.validate = !sim.validate;
+                    info!("validate {}", if sim.validate { "on" } else { "off" });
+                }
+                Action::Pause => time.toggle_pause(),
+                Action::Step => time.step_once(),
+                Action::Slower => time.slower(),
+                Action::Faster => time.faster(),
+                Action::BrushSmaller => brush = (brush - 1).max(1),
+                Action::BrushBigger => brush = (brush + 1).min(20),
+                _ => {}
             }
         }
-        //k up
-        if let Some(Button::Keyboard(key)) = event.release_args() {
-            if key == Key::Space {
-                space = false;
-            }
-            if key == Key::LShift {
-                shift = false;
-            }
-            if key == Key::LCtrl {
-                ctrl = false;
-            }
-            if key == Key::LAlt {
-                alt = false;
-            }
-            if key == Key::Tab {
-                _tab = false;
-                sim.edge_mode = !sim.edge_mode;
-            }
+        //buttons coming up
+        if let Some(Action::DrawLine(typ)) = event
+            .release_args()
+            .and_then(|button| input.release(button))
+        {
+            material = typ;
+            sim.place_line(line_start, material.clone());
         }
 
-        //middle click to to place sand
-        if middle_click {
-            sim.place_pixel("sand".to_string());
-        }
-        //right click to erase (air)
-        if right_click {
-            sim.erase(5, "air".to_string());
-        }
-        //space to place water
-        if space {
-            sim.sea("water".to_string());
-        }
-        if shift {
-            sim.place_pixel("brick".to_string());
-        }
-        if ctrl {
-            sim.place_pixel("lava".to_string());
-        }
-        if alt {
-            sim.place_pixel("wood".to_string());
+        //buttons held down
+        for action in input.held() {
+            match action {
+                Action::PlaceMaterial(typ) => {
+                    material = typ;
+                    sim.place_pixel(material.clone());
+                }
+                Action::PlaceRow(typ) => {
+                    material = typ;
+                    sim.sea(material.clone());
+                }
+                Action::Erase => sim.erase(brush, "air".to_string()),
+                //blast everything away from the mouse
+                Action::Blast => sim.apply_impulse(sim.mouse_pos, 6, 3.0),
+                _ => {}
+            }
         }
 
         //update simulation
         if let Some(_args) = event.update_args() {
-            let verbose = false;
-            if verbose {
-                println!("PRE UPDATE ++++++++++++++++++++++++++++++++++++++++++++++++scale: {}, size: {}x{}", sim.scale, sim.size[0], sim.size[1]);
-                sim.print(verbose);
+            //look around
+            let mut pan = [0.0; 2];
+            if input.is_held(&Action::PanUp) {
+                pan[1] += PAN_SPEED;
             }
-
-            let subgrids = sim.update_grids(verbose);
-            //multithread the subdate fn
-            let mut handles = vec![];
-            for i in 0..sim.chunk_div {
-                let subgrid = subgrids[i as usize].clone();
-                let handle = thread::spawn(move || {
-                    let (sg, eg, id) = subdate(
-                        subgrid,
-                        i,
-                        sim.size,
-                        sim.chunk_div,
-                        sim.gravity,
-                        sim.friction,
-                        sim.edge_mode,
-                    );
-                    (sg, eg, id)
-                });
-                handles.push(handle);
+            if input.is_held(&Action::PanDown) {
+                pan[1] -= PAN_SPEED;
+            }
+            if input.is_held(&Action::PanLeft) {
+                pan[0] += PAN_SPEED;
             }
+            if input.is_held(&Action::PanRight) {
+                pan[0] -= PAN_SPEED;
+            }
+            camera.pan(pan);
 
-            let mut subgrids2 = vec![];
-            let mut edge_cases2 = vec![];
-            let mut idorder = vec![];
-            for handle in handles {
-                let (subgrid, edge_cases, id) = handle.join().unwrap();
-                idorder.push(id);
-                subgrids2.push(subgrid);
-                edge_cases2.push(edge_cases);
+            //the whole grid in the terminal, only when tracing
+            if log_enabled!(Level::Trace) {
+                sim.print();
             }
 
-            sim.update_whole((subgrids2, idorder), edge_cases2);
+            for _ in 0..time.ticks() {
+                sim.step();
+                hud.ticks.record(std::time::Instant::now());
+                //stop on whatever broke so it can be looked at
+                if let Some(err) = sim.broken.take() {
+                    error!("{}", err);
+                    if !time.paused {
+                        time.toggle_pause();
+                    }
+                    break;
+                }
+            }
         }
     }
 }
@@ -0,0 +1,176 @@
+use piston_window::*;
+
+use crate::camera::*;
+use crate::draw::*;
+use crate::elements::*;
+use crate::grid::Grid;
+use crate::simulate::*;
+
+//what a heatmap colours cells by
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Heatmap {
+    Off,
+    Density,
+    //heat given off by anything burning
+    Temperature,
+    Pressure,
+}
+
+impl Heatmap {
+    pub fn next(self) -> Heatmap {
+        match self {
+            Heatmap::Off => Heatmap::Density,
+            Heatmap::Density => Heatmap::Temperature,
+            Heatmap::Temperature => Heatmap::Pressure,
+            Heatmap::Pressure => Heatmap::Off,
+        }
+    }
+}
+
+//debug views drawn over the grid, each toggled on its own
+#[derive(Clone, Copy, Debug)]
+pub struct Overlays {
+    //outlines round dense resting cells
+    pub outlines: bool,
+    //chunk borders, tinted by whether anything happened in them last tick
+    pub chunks: bool,
+    pub velocity: bool,
+    pub heatmap: Heatmap,
+    //cells that reacted or caught fire last tick
+    pub reactions: bool,
+}
+
+impl Overlays {
+    pub fn new() -> Overlays {
+        Overlays {
+            outlines: false,
+            chunks: false,
+            velocity: false,
+            heatmap: Heatmap::Off,
+            reactions: false,
+        }
+    }
+}
+
+//blue for low through to red for high, `t` from 0.0 to 1.0
+pub fn heat_color(t: f64) -> [f32; 4] {
+    let t = t.clamp(0.0, 1.0) as f32;
+    [t, 0.2 * (1.0 - (2.0 * t - 1.0).abs()), 1.0 - t, 0.6]
+}
+
+//heatmap colours as rgba bytes, row by row, scaled to the biggest value in the
+//world, all clear when it's off or there's nothing to show
+pub fn heatmap_rgba(grid: &Grid, heatmap: Heatmap) -> Vec<u8> {
+    let n = grid.ptype.len();
+    let value = |i: usize| match heatmap {
+        Heatmap::Off => 0.0,
+        Heatmap::Density => grid.props(i).density,
+        Heatmap::Temperature => grid.heat[i] as f64,
+        Heatmap::Pressure => grid.pressure[i] as f64,
+    };
+    let max = (0..n).map(value).fold(0.0, f64::max);
+    let mut rgba = vec![0; n * 4];
+    if max > 0.0 {
+        for (i, texel) in rgba.chunks_mut(4).enumerate() {
+            for (byte, c) in texel.iter_mut().zip(heat_color(value(i) / max)) {
+                *byte = (c * 255.0).round() as u8;
+            }
+        }
+    }
+    rgba
+}
+
+pub fn overlay_draw(
+    sim: &Simulation,
+    overlays: &Overlays,
+    camera: &Camera,
+    context: Context,
+    graphics: &mut G2d,
+) {
+    let [x0, y0, x1, y1] = camera.visible(sim.size);
+
+    if overlays.chunks {
+        for (id, tile) in sim.tiles().into_iter().enumerate() {
+            let tl = camera.screen_at([tile.origin[0] as f64, tile.origin[1] as f64]);
+            let br = camera.screen_at([
+                (tile.origin[0] + tile.size[0]) as f64,
+                (tile.origin[1] + tile.size[1]) as f64,
+            ]);
+            //green while awake, grey once nothing in it moves
+            let active = sim.stats.active_chunks.get(id).copied().unwrap_or(false);
+            let tint = if active {
+                [0.0, 1.0, 0.0, 0.12]
+            } else {
+                [0.3, 0.3, 0.3, 0.25]
+            };
+            rectangle(
+                tint,
+                [tl[0], tl[1], br[0] - tl[0], br[1] - tl[1]],
+                context.transform,
+                graphics,
+            );
+            draw_outline([tl[0], tl[1], br[0], br[1]], context, graphics);
+        }
+    }
+
+    if overlays.velocity {
+        let arrow = line::Line::new([1.0, 0.0, 0.0, 1.0], 1.0);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let i = sim.grid.index([x, y]);
+                let vel = sim.grid.velocity(i);
+                if sim.grid.props(i).phase == Phase::Static || vel == [0.0; 2] {
+                    continue;
+                }
+                //from the middle of the cell to where it's heading next tick
+                let from = camera.screen_at([x as f64 + 0.5, y as f64 + 0.5]);
+                let to = camera.screen_at([x as f64 + 0.5 + vel[0], y as f64 + 0.5 + vel[1]]);
+                arrow.draw(
+                    [from[0], from[1], to[0], to[1]],
+                    &context.draw_state,
+                    context.transform,
+                    graphics,
+                );
+            }
+        }
+    }
+
+    if overlays.reactions {
+        for pos in &sim.stats.reacted {
+            if pos[0] < x0 || pos[0] >= x1 || pos[1] < y0 || pos[1] >= y1 {
+                continue;
+            }
+            let rect = camera.cell_rect(*pos);
+            let square = rectangle::square(rect[0], rect[1], camera.zoom);
+            rectangle([1.0, 1.0, 0.0, 0.7], square, context.transform, graphics);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heat_runs_blue_to_red() {
+        assert_eq!(heat_color(0.0)[2], 1.0);
+        assert_eq!(heat_color(1.0)[0], 1.0);
+        assert_eq!(heat_color(5.0), heat_color(1.0));
+        assert_eq!(Heatmap::Off.next().next(), Heatmap::Temperature);
+        assert_eq!(Heatmap::Off.next().next().next().next(), Heatmap::Off);
+    }
+
+    #[test]
+    fn temperature_heatmap_is_clear_until_something_burns() {
+        let mut sim = Simulation::new();
+        let rgba = heatmap_rgba(&sim.grid, Heatmap::Temperature);
+        assert_eq!(rgba.len(), sim.grid.ptype.len() * 4);
+        assert!(rgba.iter().all(|byte| *byte == 0));
+
+        let i = sim.grid.index([3, 2]);
+        sim.grid.heat[i] = 40;
+        let rgba = heatmap_rgba(&sim.grid, Heatmap::Temperature);
+        assert_eq!(rgba[i * 4], 255);
+        assert_eq!(rgba[(i + 1) * 4 + 3], 153);
+    }
+}
@@ -1,80 +1,40 @@
 use piston_window::*;
+use rand::Rng;
 
+use crate::camera::*;
 use crate::draw::*;
 
 use crate::elements::*;
+use crate::grid::Grid;
+use crate::random;
 
+//a freshly spawned pixel of some element, what gets put in the grid,
+//everything an element always has lives in the element table
 #[derive(Clone, Copy)]
 pub struct Pixel {
     pub ptype: u8,
-    pub pos: [u32; 2],
-    pub vel: [f64; 2],
-    pub color: [f32; 4],
-    pub density: f64,
-    pub min_force: f64,
-    pub gravity_multiplier: f64,
-    pub friction_multiplier: f64,
+    //ticks left to burn, only flames start out burning
+    pub life: u8,
 }
 
 impl Pixel {
-    pub fn new(
-        ptype: u8,
-        pos: [u32; 2],
-        vel: [f64; 2],
-        color: [f32; 4],
-        density: f64,
-        min_force: f64,
-        gravity_multiplier: f64,
-        friction_multiplier: f64,
-    ) -> Pixel {
-        Pixel {
-            ptype,
-            pos,
-            vel,
-            color,
-            density,
-            min_force,
-            gravity_multiplier,
-            friction_multiplier,
-        }
-    }
-
-    pub fn spawn(typ: String, pos: [u32; 2]) -> Pixel {
-        if typ == "air" {
-            air(pos)
-        } else if typ == "sand" {
-            sand(pos)
-        } else if typ == "water" {
-            water(pos)
-        } else if typ == "lava" {
-            lava(pos)
-        } else if typ == "stone" {
-            stone(pos)
-        } else if typ == "brick" {
-            brick(pos)
-        } else if typ == "wood" {
-            wood(pos)
-        } else if typ == "smoke" {
-            smoke(pos)
-        } else if typ == "glass" {
-            glass(pos)
+    pub fn spawn(typ: String) -> Pixel {
+        let Some(ptype) = NAMES.iter().position(|name| *name == typ) else {
+            return Pixel::default();
+        };
+        let ptype = ptype as u8;
+        let [low, high] = element(ptype).lifespan;
+        let life = if high > low {
+            random::rng().gen_range(low..high)
         } else {
-            Pixel::default()
-        }
+            low
+        };
+        Pixel { ptype, life }
     }
 
     pub fn default() -> Pixel {
         //null pixel, should only be used to instantiate empty grid, then replaced
-        Pixel {
-            ptype: 0,
-            pos: [0, 0],
-            vel: [0.0, 0.0],
-            color: [1.0, 0.0, 1.0, 1.0],
-            density: 0.0,
-            min_force: 0.0,
-            gravity_multiplier: 0.0,
-            friction_multiplier: 0.0,
-        }
+        Pixel { ptype: 0, life: 0 }
     }
 
     // pub fn print(&self) -> String {
@@ -118,34 +78,16 @@ impl Pixel {
     //     }
     //     return printstr.to_string();
     // }
-
-    pub fn draw(&self, scale: u32) -> ([f64; 4], [f32; 4]) {
-        let coords = screem(self.pos, scale);
-        let tx = coords[0];
-        let ty = coords[1];
-
-        return (coords, self.color);
-    }
 }
 
-pub fn pixel_draw(pixels: &Vec<Vec<Pixel>>, context: Context, graphics: &mut G2d, scale: u32) {
-    for (_y, row) in pixels.iter().enumerate() {
-        for (_x, pixel) in row.iter().enumerate() {
-            let mut ruler = false;
-
-            if pixel.density > 0.95 && (pixel.vel[0] == 0.0 && pixel.vel[1] == 0.0) {
-                ruler = true;
-            }
-
-            let (coords, color) = pixel.draw(scale);
-
-            let square = rectangle::square(coords[0], coords[1], scale as f64);
-
-            rectangle(color, square, context.transform, graphics);
-
-            if ruler {
-                //draw outline
-                draw_cursor_outline(coords, context, graphics);
+//outline dense resting cells, only the ones the camera can see
+pub fn outline_draw(grid: &Grid, context: Context, graphics: &mut G2d, camera: &Camera) {
+    let [x0, y0, x1, y1] = camera.visible(grid.size);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let i = grid.index([x, y]);
+            if grid.props(i).density > 0.95 && grid.velocity(i) == [0.0; 2] {
+                draw_cursor_outline(camera.cell_rect([x, y]), context, graphics);
             }
         }
     }
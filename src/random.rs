@@ -0,0 +1,95 @@
+use rand::rngs::StdRng;
+use rand::{Error, RngCore, SeedableRng};
+
+use std::cell::{Cell, RefCell};
+
+//every thread has its own generator, seeded from entropy until told otherwise,
+//chunk threads are seeded from the main thread's seed, the tick and where the
+//chunk is, so a seeded run always plays out the same way whichever thread
+//gets there first
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+    static SEED: Cell<u64> = Cell::new(rand::random());
+}
+
+pub fn seed(seed: u64) {
+    SEED.with(|s| s.set(seed));
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+//the seed for the chunk at `chunk` on tick `tick`, from this thread's seed
+pub fn chunk_seed(tick: u64, chunk: [u32; 2]) -> u64 {
+    let mut h = SEED.with(Cell::get);
+    for v in [tick, chunk[0] as u64, chunk[1] as u64] {
+        h = mix(h ^ v);
+    }
+    h
+}
+
+//splitmix64, nearby inputs come out nowhere near each other
+fn mix(v: u64) -> u64 {
+    let mut z = v.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+//the generator for anything that changes how the simulation plays out,
+//colors don't matter and use rand::thread_rng
+pub fn rng() -> SimRng {
+    SimRng
+}
+
+//a handle on this thread's generator, used like rand::thread_rng()
+pub struct SimRng;
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        RNG.with(|rng| rng.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        RNG.with(|rng| rng.borrow_mut().fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        RNG.with(|rng| rng.borrow_mut().try_fill_bytes(dest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let draw = || {
+            (0..8)
+                .map(|_| rng().gen_range(0..1000))
+                .collect::<Vec<u32>>()
+        };
+        seed(7);
+        let first = draw();
+        seed(7);
+        assert_eq!(draw(), first);
+        seed(8);
+        assert_ne!(draw(), first);
+    }
+
+    #[test]
+    fn chunk_seeds_depend_on_tick_and_place_only() {
+        seed(7);
+        let first = chunk_seed(3, [1, 0]);
+        rng().gen_range(0..1000);
+        assert_eq!(chunk_seed(3, [1, 0]), first);
+        assert_ne!(chunk_seed(4, [1, 0]), first);
+        assert_ne!(chunk_seed(3, [0, 1]), first);
+        seed(8);
+        assert_ne!(chunk_seed(3, [1, 0]), first);
+    }
+}
@@ -0,0 +1,205 @@
+use log::info;
+
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+use crate::grid::Grid;
+use crate::pixel::*;
+use crate::simulate::*;
+
+//saves are plain text
+//size <w> <h>
+//boundary <x> <y>   (wall, wrap, void or open)
+//emitter <x> <y> <element> <rate>   (one per emitter)
+//grid
+//<h rows of w element codes>
+//only element codes are kept, colors are rerolled and everything starts at rest
+
+fn bad_data(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+impl Simulation {
+    pub fn save(&self, path: &str) -> Result<()> {
+        fs::write(path, self.save_text())?;
+        info!("saved {}", path);
+        Ok(())
+    }
+
+    //what save writes
+    pub fn save_text(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("size {} {}\n", self.size[0], self.size[1]));
+        out.push_str(&format!(
+            "boundary {} {}\n",
+            self.boundary[0].name(),
+            self.boundary[1].name()
+        ));
+        for (pos, emitter) in &self.emitters {
+            out.push_str(&format!(
+                "emitter {} {} {} {}\n",
+                pos[0],
+                pos[1],
+                self.elements.get_name(emitter.element),
+                emitter.rate
+            ));
+        }
+        out.push_str("grid\n");
+        for y in 0..self.size[1] {
+            let codes: Vec<String> = self
+                .grid
+                .row(y)
+                .iter()
+                .map(|code| code.to_string())
+                .collect();
+            out.push_str(&codes.join(" "));
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn load(&mut self, path: &str) -> Result<()> {
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines();
+
+        let mut size = self.size;
+        let mut boundary = self.boundary;
+        let mut emitters = vec![];
+        for line in lines.by_ref() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["size", w, h] => {
+                    size = [
+                        w.parse()
+                            .map_err(|_| bad_data(format!("bad size {}", line)))?,
+                        h.parse()
+                            .map_err(|_| bad_data(format!("bad size {}", line)))?,
+                    ];
+                }
+                ["boundary", x, y] => {
+                    boundary = [
+                        Boundary::parse(x)
+                            .ok_or_else(|| bad_data(format!("bad boundary {}", line)))?,
+                        Boundary::parse(y)
+                            .ok_or_else(|| bad_data(format!("bad boundary {}", line)))?,
+                    ];
+                }
+                //older saves only wrapped or didn't
+                ["edge_mode", mode] => {
+                    let wrap: bool = mode
+                        .parse()
+                        .map_err(|_| bad_data(format!("bad edge mode {}", line)))?;
+                    boundary = if wrap {
+                        [Boundary::Wrap; 2]
+                    } else {
+                        [Boundary::Wall; 2]
+                    };
+                }
+                ["emitter", x, y, element, rate] => {
+                    let pos: [u32; 2] = [
+                        x.parse()
+                            .map_err(|_| bad_data(format!("bad emitter {}", line)))?,
+                        y.parse()
+                            .map_err(|_| bad_data(format!("bad emitter {}", line)))?,
+                    ];
+                    let element = self.elements._get(element.to_string());
+                    if element == 0 {
+                        return Err(bad_data(format!("unknown emitter element {}", line)));
+                    }
+                    let rate = rate
+                        .parse()
+                        .map_err(|_| bad_data(format!("bad emitter {}", line)))?;
+                    emitters.push((pos, Emitter { element, rate }));
+                }
+                ["grid"] => break,
+                [] => continue,
+                _ => return Err(bad_data(format!("unknown line {}", line))),
+            }
+        }
+
+        //chunks have to split the grid evenly
+        let n = (self.chunk_div as f64).sqrt() as u32;
+        if size[0] == 0 || size[1] == 0 || !size[0].is_multiple_of(n) || !size[1].is_multiple_of(n)
+        {
+            return Err(bad_data(format!(
+                "size {}x{} doesn't split into {} chunks",
+                size[0], size[1], self.chunk_div
+            )));
+        }
+
+        let mut grid = Grid::new(size, self.elements.props.clone());
+        for y in 0..size[1] {
+            let line = lines
+                .next()
+                .ok_or_else(|| bad_data(format!("missing grid row {}", y)))?;
+            let mut len = 0;
+            for (x, code) in line.split_whitespace().enumerate() {
+                if x >= size[0] as usize {
+                    return Err(bad_data(format!("grid row {} is the wrong length", y)));
+                }
+                let code: u8 = code
+                    .parse()
+                    .map_err(|_| bad_data(format!("bad element {} at {} {}", code, x, y)))?;
+                let typ = self.elements.get_name(code);
+                if code == 0 || typ == "default" {
+                    return Err(bad_data(format!("unknown element {} at {} {}", code, x, y)));
+                }
+                grid.set([x as u32, y], Pixel::spawn(typ));
+                len += 1;
+            }
+            if len != size[0] as usize {
+                return Err(bad_data(format!("grid row {} is the wrong length", y)));
+            }
+        }
+
+        info!("loaded {} {}x{}", path, size[0], size[1]);
+        self.size = size;
+        self.boundary = boundary;
+        self.grid = grid;
+        self.emitters.clear();
+        for (pos, emitter) in emitters {
+            if pos[0] < size[0] && pos[1] < size[1] {
+                self.emitters.insert(pos, emitter);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_round_trips() {
+        let mut sim = Simulation::new();
+        sim.set_pixel([3, 4], "sand".to_string());
+        sim.emitter.rate = 0.25;
+        sim.set_pixel([5, 6], "emitter".to_string());
+        sim.set_pixel([7, 8], "sink".to_string());
+        sim.boundary = [Boundary::Wrap, Boundary::Open];
+        let path = std::env::temp_dir().join("particle_save_round_trip.txt");
+        let path = path.to_str().unwrap();
+        sim.save(path).unwrap();
+
+        let mut loaded = Simulation::new();
+        loaded.load(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.grid.ptype, sim.grid.ptype);
+        assert_eq!(loaded.emitters, sim.emitters);
+        assert_eq!(loaded.boundary, sim.boundary);
+    }
+
+    #[test]
+    fn load_rejects_bad_grid() {
+        let path = std::env::temp_dir().join("particle_save_bad.txt");
+        let path = path.to_str().unwrap();
+        fs::write(path, "size 2 2\ngrid\n1 1\n1 99\n").unwrap();
+
+        let mut sim = Simulation::new();
+        let err = sim.load(path).unwrap_err();
+        fs::remove_file(path).unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
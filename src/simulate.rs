@@ -1,191 +1,615 @@
 use crossterm::{cursor, terminal, QueueableCommand};
-use piston_window::*;
+use log::{debug, trace};
 use rand::Rng;
 
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::io::stdout;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use crate::draw::*;
 use crate::elements::*;
+use crate::fixed::{Constants, Fx, Math, Num};
+use crate::grid::Grid;
 use crate::pixel::*;
+use crate::random;
+use crate::stats::*;
+
+//a source cell, spawns `element` into empty neighbours with chance `rate` each tick
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Emitter {
+    pub element: u8,
+    pub rate: f64,
+}
+
+//what happens at the edge of the world, set per axis
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    //nothing gets past
+    Wall,
+    //comes back in on the other side
+    Wrap,
+    //anything that moves past it is deleted
+    Void,
+    //endless air outside, whatever would move into air leaves and air takes its place
+    Open,
+}
+
+impl Boundary {
+    pub fn name(self) -> &'static str {
+        match self {
+            Boundary::Wall => "wall",
+            Boundary::Wrap => "wrap",
+            Boundary::Void => "void",
+            Boundary::Open => "open",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Boundary> {
+        match name {
+            "wall" => Some(Boundary::Wall),
+            "wrap" => Some(Boundary::Wrap),
+            "void" => Some(Boundary::Void),
+            "open" => Some(Boundary::Open),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Boundary {
+        match self {
+            Boundary::Wall => Boundary::Wrap,
+            Boundary::Wrap => Boundary::Void,
+            Boundary::Void => Boundary::Open,
+            Boundary::Open => Boundary::Wall,
+        }
+    }
+}
 
 pub struct Simulation {
     pub size: [u32; 2],
     pub scale: u32,
     pub chunk_div: u32,
-    pub window: PistonWindow,
-    pub grid: Vec<Vec<Pixel>>,
+    pub grid: Grid,
     pub gravity: f64,
     pub friction: f64,
     pub mouse_pos: [u32; 2],
-    pub edge_mode: bool,
+    //x and y edges
+    pub boundary: [Boundary; 2],
     pub elements: ElementList,
+    //emitter cells by position
+    pub emitters: BTreeMap<[u32; 2], Emitter>,
+    //what newly placed emitters spawn
+    pub emitter: Emitter,
+    pub stats: Stats,
+    //check invariants after every tick, off unless asked for, it's slow
+    pub validate: bool,
+    //the first invariant broken, validate turns itself off after one so
+    //it's only reported once
+    pub broken: Option<String>,
+    //fixed point movement gives the same grid on every build and thread
+    //count, for lockstep play
+    pub math: Math,
+}
+
+//side of the tiles fixed point runs move in, whatever chunk_div is, the
+//last row and column of tiles get what's left over
+const FIXED_TILE: u32 = 20;
+
+//a piece of the grid moved on its own thread
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    //column and row in the layout
+    pub place: [u32; 2],
+    pub origin: [u32; 2],
+    pub size: [u32; 2],
 }
 
 impl Simulation {
     pub fn new() -> Simulation {
-        let elements = ElementList::new();
-
-        const SCALE: u32 = 10;
         const CHUNK_DIV: u32 = 4;
         const SIM_SIZE: u32 = 20;
         let size: [u32; 2] = [
             SIM_SIZE * (CHUNK_DIV as f64).sqrt() as u32,
             SIM_SIZE * (CHUNK_DIV as f64).sqrt() as u32,
         ];
+        Simulation::with_size(size, CHUNK_DIV)
+    }
+
+    //an empty grid split into `chunk_div` chunks, one thread each, chunk_div
+    //has to be a square number whose root divides both sides, fixed point
+    //runs only take it as the number of threads
+    pub fn with_size(size: [u32; 2], chunk_div: u32) -> Simulation {
+        let elements = ElementList::new();
+
+        const SCALE: u32 = 10;
 
         let gravity: f64 = 1.0;
         let friction: f64 = 0.99;
         let mouse_pos = [0, 0];
-        let edge_mode: bool = false;
+        let boundary = [Boundary::Wall; 2];
 
-        let mut grid: Vec<Vec<Pixel>> = vec![];
-
-        //SET pos in pixels to index in grid
-        for y in 0..size[1] {
-            let mut row: Vec<Pixel> = vec![];
-            for x in 0..size[0] {
-                row.push(Pixel::spawn("air".to_string(), [x, y]));
-            }
-            grid.push(row);
-        }
-
-        //temp check to make sure air is set
-
-        let window: PistonWindow =
-            WindowSettings::new("Pixel Simulation", [size[0] * SCALE, size[1] * SCALE])
-                .exit_on_esc(true)
-                .build()
-                .unwrap();
+        let grid = Grid::new(size, elements.props.clone());
 
         Simulation {
-            size: size,
+            size,
             scale: SCALE,
-            window,
             grid,
-            chunk_div: CHUNK_DIV,
+            chunk_div,
             gravity,
             friction,
             mouse_pos,
-            edge_mode,
+            boundary,
             elements,
+            emitters: BTreeMap::new(),
+            emitter: Emitter {
+                element: WATER,
+                rate: 0.5,
+            },
+            stats: Stats::default(),
+            validate: false,
+            broken: None,
+            math: Math::Float,
         }
     }
-    //returns the subgrid and the edge cases
-
-    pub fn update_grids(&mut self, _verbose: bool) -> Vec<Vec<Vec<Pixel>>> {
-        let mut subgrids: Vec<Vec<Vec<Pixel>>> = vec![];
-
-        //split grid into subgrids
-        for x in 0..(self.chunk_div as f64).sqrt() as u32 {
-            for y in 0..(self.chunk_div as f64).sqrt() as u32 {
-                println!("STEP {} {}", x, y);
-                //trim self.grid.clone to self.size / chunk_div + offset
-                let mut subgrid: Vec<Vec<Pixel>> = vec![];
-                for y2 in 0..self.size[1] / (self.chunk_div as f64).sqrt() as u32 {
-                    let mut row: Vec<Pixel> = vec![];
-                    for x2 in 0..self.size[0] / (self.chunk_div as f64).sqrt() as u32 {
-                        row.push(
-                            self.grid[(y * self.size[1] / self.chunk_div + y2) as usize]
-                                [(x * self.size[0] / self.chunk_div + x2) as usize]
-                                .clone(),
-                        );
-                    }
-                    subgrid.push(row);
-                }
 
-                println!("SG CHECK {} {}", subgrid.len(), subgrid[0].len());
+    //how movement splits the grid, row by row, float runs into the
+    //chunk_div chunks, fixed point runs into FIXED_TILE tiles so the
+    //number of threads can't change what moves where
+    pub fn tiles(&self) -> Vec<Tile> {
+        let (side, n) = match self.math {
+            Math::Float => {
+                let n = (self.chunk_div as f64).sqrt() as u32;
+                ([self.size[0] / n, self.size[1] / n], [n, n])
+            }
+            Math::Fixed => (
+                [FIXED_TILE; 2],
+                [0, 1].map(|axis| self.size[axis].div_ceil(FIXED_TILE)),
+            ),
+        };
+        let mut tiles = vec![];
+        for y in 0..n[1] {
+            for x in 0..n[0] {
+                let origin = [x * side[0], y * side[1]];
+                let size = [0, 1].map(|axis| side[axis].min(self.size[axis] - origin[axis]));
+                trace!("tile {} {}", x, y);
+                tiles.push(Tile {
+                    place: [x, y],
+                    origin,
+                    size,
+                });
+            }
+        }
+        tiles
+    }
+
+    //work out the pressure in every cell, liquids from the weight of liquid
+    //above them, gases from the gas around them, the movement rules move
+    //things from high pressure to low
+    pub fn update_pressure<N: Num>(&mut self) {
+        let w = self.size[0] as usize;
+        let h = self.size[1] as usize;
+
+        //column weight
+        for x in 0..w {
+            let mut weight = N::ZERO;
+            for y in 0..h {
+                let i = self.grid.index([x as u32, y as u32]);
+                let props = *self.grid.props(i);
+                if props.phase == Phase::Liquid {
+                    weight = weight + N::movement(&props).density;
+                    self.grid.pressure[i] = weight.to_f64() as f32;
+                } else {
+                    weight = N::ZERO;
+                    self.grid.pressure[i] = 0.0;
+                }
+            }
+        }
 
-                subgrids.push(subgrid);
+        //connected bodies of liquid, the deep end of a u tube feels the
+        //weight of the whole body not just the column above it
+        let mut seen = vec![vec![false; w]; h];
+        for y in 0..h {
+            for x in 0..w {
+                if seen[y][x] || self.grid.props_at([x as u32, y as u32]).phase != Phase::Liquid {
+                    continue;
+                }
+                let body = self.liquid_body([x as u32, y as u32], &mut seen);
+                let top = body.iter().map(|pos| pos[1]).min().unwrap();
+                for pos in &body {
+                    let i = self.grid.index(*pos);
+                    let depth = N::of((pos[1] - top + 1) as f64);
+                    let head = (N::movement(self.grid.props(i)).density * depth).to_f64() as f32;
+                    if head > self.grid.pressure[i] {
+                        self.grid.pressure[i] = head;
+                    }
+                }
             }
         }
 
-        subgrids
+        //gases, the average density of the gas around them
+        for y in 0..h {
+            for x in 0..w {
+                let i = self.grid.index([x as u32, y as u32]);
+                if self.grid.props(i).phase != Phase::Gas {
+                    continue;
+                }
+                let mut total = N::movement(self.grid.props(i)).density;
+                let mut n = 1.0;
+                for a in adjacents([x as u32, y as u32], self.boundary, self.size) {
+                    let other = self.grid.props_at(a);
+                    if other.phase == Phase::Gas {
+                        total = total + N::movement(other).density;
+                        n += 1.0;
+                    }
+                }
+                self.grid.pressure[i] = (total / N::of(n)).to_f64() as f32;
+            }
+        }
     }
 
-    pub fn update_whole(
-        &mut self,
-        mut sgi: (Vec<Vec<Vec<Pixel>>>, Vec<u32>),
-        mut edge_cases: Vec<Vec<Pixel>>,
-    ) {
-        //assemble subgrids into grid, each subgrid is a chunk of the grid
-        let mut new_grid: Vec<Vec<Pixel>> = self.grid.clone();
+    //every liquid pixel connected to `start`, marking them as seen
+    pub fn liquid_body(&self, start: [u32; 2], seen: &mut [Vec<bool>]) -> Vec<[u32; 2]> {
+        let mut body = vec![];
+        let mut stack = vec![start];
+        seen[start[1] as usize][start[0] as usize] = true;
+        while let Some(pos) = stack.pop() {
+            body.push(pos);
+            for d in [[-1, 0], [1, 0], [0, -1], [0, 1]] {
+                let a = wrapped_coord(
+                    [pos[0] as i32 + d[0], pos[1] as i32 + d[1]],
+                    self.boundary,
+                    self.size,
+                );
+                if !seen[a[1] as usize][a[0] as usize]
+                    && self.grid.props_at(a).phase == Phase::Liquid
+                {
+                    seen[a[1] as usize][a[0] as usize] = true;
+                    stack.push(a);
+                }
+            }
+        }
+        body
+    }
 
-        let (subgrids, ids) = sgi;
+    //advance the simulation one tick, chunks are moved on their own threads
+    pub fn step(&mut self) {
+        self.grid.set_math(self.math);
+        match self.math {
+            Math::Float => self.step_in::<f64>(),
+            Math::Fixed => self.step_in::<Fx>(),
+        }
+    }
 
-        for id in ids {
-            let coord = id_coord(id, (self.chunk_div as f64).sqrt() as u32);
-            let subgrid = &subgrids[id as usize];
+    fn step_in<N: Num>(&mut self) {
+        let before: Vec<u8> = self.grid.ptype.clone();
+        self.stats.reactions = 0;
+        self.stats.reacted.clear();
+
+        let mut times = PhaseTimes::default();
+        let mut lap = Instant::now();
+        let mut time = |phase: &mut Duration| {
+            let now = Instant::now();
+            *phase = now - lap;
+            lap = now;
+        };
+
+        self.update_pressure::<N>();
+        time(&mut times.pressure);
+        let tiles = self.tiles();
+        let subgrids: Vec<Grid> = tiles
+            .iter()
+            .map(|tile| self.grid.chunk(tile.origin, tile.size))
+            .collect();
+        time(&mut times.split);
+
+        let constants = Constants::<N>::of(self.gravity, self.friction, FLUNG_SPEED);
+        //every thread takes every threads'th tile, each tile seeded from the
+        //tick and where it is rather than which thread runs it or when
+        let threads = (self.chunk_div as usize).clamp(1, tiles.len());
+        let mut jobs: Vec<Vec<(u32, Grid, u64)>> = (0..threads).map(|_| vec![]).collect();
+        for (i, (tile, subgrid)) in tiles.iter().zip(subgrids).enumerate() {
+            let seed = random::chunk_seed(self.stats.tick, tile.place);
+            jobs[i % threads].push((i as u32, subgrid, seed));
+        }
+        let mut handles = vec![];
+        for job in jobs {
+            let handle = thread::spawn(move || {
+                job.into_iter()
+                    .map(|(id, subgrid, seed)| {
+                        random::seed(seed);
+                        subdate(subgrid, id, constants)
+                    })
+                    .collect::<Vec<_>>()
+            });
+            handles.push(handle);
+        }
+        let mut results = vec![];
+        for handle in handles {
+            results.extend(handle.join().unwrap());
+        }
+        //back in tile order whichever thread moved them
+        results.sort_by_key(|(_, _, id)| *id);
+        time(&mut times.chunks);
+
+        self.update_whole(&tiles, results, constants);
+        time(&mut times.whole);
+        let leaky = self
+            .boundary
+            .iter()
+            .any(|edge| matches!(edge, Boundary::Void | Boundary::Open));
+        if self.validate && !leaky {
+            //moving never creates or destroys anything, unless it goes out
+            //through an edge
+            if let Err(err) = self.check_conserved(&self.count_elements(), &before) {
+                self.broken = Some(format!("tick {}: {}", self.stats.tick + 1, err));
+                self.validate = false;
+            }
+        }
 
-            for y in 0..subgrid.len() {
-                for x in 0..subgrid[0].len() {
-                    new_grid[(coord[1] as usize * subgrid.len() + y) as usize]
-                        [(coord[0] as usize * subgrid[0].len() + x) as usize] =
-                        subgrid[y][x].clone();
-                }
+        self.react();
+        time(&mut times.react);
+        self.update_stats(&before);
+        time(&mut times.stats);
+        self.stats.times = times;
+        if self.validate {
+            if let Err(err) = self.check_invariants() {
+                self.broken = Some(format!("tick {}: {}", self.stats.tick, err));
+                self.validate = false;
             }
         }
-        //get list of pixels ordered by and velocity
+        debug!(
+            "tick {} active {} reactions {} avg_vel {:.3}",
+            self.stats.tick, self.stats.active, self.stats.reactions, self.stats.avg_vel
+        );
+    }
+
+    pub fn update_whole<N: Num>(
+        &mut self,
+        tiles: &[Tile],
+        results: Vec<(Grid, Vec<[u32; 2]>, u32)>,
+        constants: Constants<N>,
+    ) {
+        //assemble subgrids into grid, one per tile in tile order, edge cases
+        //come back as positions in their tile
         let mut pixel_list = Vec::new();
 
-        for list in edge_cases {
-            for pixel in list {
-                pixel_list.push(pixel);
+        for (tile, (subgrid, list, _)) in tiles.iter().zip(results) {
+            let origin = tile.origin;
+            self.grid.put_chunk(origin, &subgrid);
+            for at in list {
+                pixel_list.push([origin[0] + at[0], origin[1] + at[1]]);
             }
         }
-
+        trace!("{} edge cases", pixel_list.len());
+
+        //pushes first, before anything else moves the surface at the other
+        //end of their body, then the rest ordered by velocity, the sort is
+        //stable so ties keep tile order
+        let grid = &self.grid;
+        let not_pushed = |at: [u32; 2]| {
+            pushed_up(
+                grid.props_at(at),
+                at,
+                grid,
+                self.boundary,
+                self.size,
+                &constants,
+            )
+            .is_none()
+        };
         pixel_list.sort_by(|a, b| {
-            ((a.vel[0] + a.vel[1]) * a.density)
-                .partial_cmp(&((b.vel[0] + b.vel[1]) * b.density))
-                .unwrap()
+            not_pushed(*a)
+                .cmp(&not_pushed(*b))
+                .then_with(|| momentum::<N>(grid, *a).total_cmp(momentum::<N>(grid, *b)))
         });
 
-        //update pixels in order
-        for pix in pixel_list {
-            let new_pos = [
-                pix.pos[0] as i32 + pix.vel[0] as i32,
-                pix.pos[1] as i32 + pix.vel[1] as i32,
-            ];
-            let pos = wrapped_coord(new_pos, self.edge_mode, self.size);
-
-            println!("WHOLE DATA {}/{}", pix.pos[0], pix.pos[1]);
-            new_grid = swap_pix(pix.pos, pos, &mut new_grid, &self.grid)
+        //edge cases can cross chunks, move them on the whole grid
+        let mut moved = vec![vec![false; self.size[0] as usize]; self.size[1] as usize];
+        for at in pixel_list {
+            move_pix(
+                at,
+                &mut self.grid,
+                &mut moved,
+                self.boundary,
+                self.size,
+                &constants,
+            );
         }
+    }
+
+    //reactions, burning, emitters and sinks, the only things that change
+    //what elements are in the grid
+    pub fn react(&mut self) {
+        //taken out so the rules can borrow the rest of the simulation, an
+        //empty grid holds its place
+        let empty = Grid::new([0, 0], self.elements.props.clone());
+        let mut new_grid = std::mem::replace(&mut self.grid, empty);
+
         //check for interactions
         for y in 0..self.size[1] {
             for x in 0..self.size[0] {
                 let pos = [x, y];
-                let interact = self.check_interacts(pos, new_grid.clone());
+                let interact = self.check_interacts(pos, &new_grid);
                 if interact.0 != "none" {
+                    self.stats.reactions += 1;
+                    self.stats.reacted.push(pos);
                     let t1 = interact.0;
                     let t2 = interact.1;
                     let pos2 = interact.2;
 
-                    new_grid[pos[1] as usize][pos[0] as usize] = Pixel::spawn(t1, pos);
+                    new_grid.set(pos, Pixel::spawn(t1));
                     if t2 != "none" {
-                        new_grid[pos2[1] as usize][pos2[0] as usize] = Pixel::spawn(t2, pos)
+                        new_grid.set(pos2, Pixel::spawn(t2))
                     }
                 }
             }
         }
+        //burn flammable pixels
+        self.combust(&mut new_grid);
+        self.sources(&mut new_grid);
 
         self.grid = new_grid;
     }
 
-    pub fn check_interacts(
-        &self,
-        pos: [u32; 2],
-        grid: Vec<Vec<Pixel>>,
-    ) -> (String, String, [u32; 2]) {
+    //heat the neighbours of anything burning, light fuel that got hot enough,
+    //burn off fuel and age flames
+    pub fn combust(&mut self, grid: &mut Grid) {
+        let mut rng = random::rng();
+        //read from the last state so fire spreads at most one pixel per tick,
+        //what's burning only needs the codes and the fuel left
+        let (ptypes, lives) = (grid.ptype.clone(), grid.life.clone());
+        //explosives that caught this tick
+        let mut blasts = vec![];
+
+        for heat in grid.heat.iter_mut() {
+            *heat = (*heat as u32 * HEAT_KEPT / 256) as u8;
+        }
+
+        for y in 0..self.size[1] {
+            for x in 0..self.size[0] {
+                let pos = [x, y];
+                let i = grid.index(pos);
+                let (ptype, life) = (ptypes[i], lives[i]);
+                //lava lights things but never burns out itself
+                if !grid.burns(ptype, life) && ptype != LAVA {
+                    continue;
+                }
+
+                let adj = adjacents(pos, self.boundary, self.size);
+                let mut doused = false;
+                for a in &adj {
+                    let n = grid.index(*a);
+                    if grid.ptype[n] == WATER {
+                        doused = true;
+                    }
+                    grid.heat[n] = grid.heat[n].saturating_add(grid.element(ptype).heat_output);
+                }
+                if ptype == LAVA {
+                    continue;
+                }
+
+                if ptype == FIRE {
+                    //flame dies down to smoke or nothing
+                    if life <= 1 {
+                        let t = if rng.gen_bool(0.5) { "smoke" } else { "air" };
+                        grid.set(pos, Pixel::spawn(t.to_string()));
+                    } else {
+                        grid.life[i] = life - 1;
+                    }
+                    continue;
+                }
+
+                //burning fuel, water puts it out
+                if doused {
+                    grid.life[i] = 0;
+                    continue;
+                }
+                if life <= 1 {
+                    //fuel used up, last flame
+                    grid.set(pos, Pixel::spawn("fire".to_string()));
+                    continue;
+                }
+                grid.life[i] = life - 1;
+
+                //flames lick upwards, smoke goes anywhere there is air
+                for a in adj {
+                    if grid.ptype_at(a) != AIR {
+                        continue;
+                    }
+                    if a[1] < y && rng.gen_bool(0.3) {
+                        grid.set(a, Pixel::spawn("fire".to_string()));
+                    } else if rng.gen_bool(0.05) {
+                        grid.set(a, Pixel::spawn("smoke".to_string()));
+                    }
+                }
+            }
+        }
+
+        //fuel catches more readily the hotter it is
+        for n in 0..grid.ptype.len() {
+            let props = *grid.props(n);
+            if props.flammability <= 0.0 || grid.heat[n] == 0 || grid.life[n] > 0 {
+                continue;
+            }
+            let chance = props.flammability * grid.heat[n] as f64 / IGNITE_HEAT;
+            if rng.gen_range(0.0..1.0) < chance && grid.ignite(n) {
+                let pos = grid.pos_of(n);
+                self.stats.reactions += 1;
+                self.stats.reacted.push(pos);
+                if props.blast > 0.0 {
+                    blasts.push((pos, props.blast));
+                }
+            }
+        }
+
+        for (pos, blast) in blasts {
+            grid.set(pos, Pixel::spawn("fire".to_string()));
+            impulse(grid, pos, blast as u32, blast, self.boundary, self.size);
+        }
+    }
+
+    //emitters spawn into empty neighbours, sinks swallow whatever reaches them
+    pub fn sources(&mut self, grid: &mut Grid) {
+        let mut rng = random::rng();
+
+        //forget emitters that were blown up or reacted away
+        self.emitters
+            .retain(|pos, _| grid.ptype_at(*pos) == EMITTER);
+        for (pos, emitter) in &self.emitters {
+            if rng.gen_range(0.0..1.0) >= emitter.rate {
+                continue;
+            }
+            let empty: Vec<[u32; 2]> = adjacents(*pos, self.boundary, self.size)
+                .into_iter()
+                .filter(|a| grid.ptype_at(*a) == AIR)
+                .collect();
+            if empty.is_empty() {
+                continue;
+            }
+            let a = empty[rng.gen_range(0..empty.len())];
+            grid.set(a, Pixel::spawn(self.elements.get_name(emitter.element)));
+        }
+
+        for y in 0..self.size[1] {
+            for x in 0..self.size[0] {
+                if grid.ptype_at([x, y]) != SINK {
+                    continue;
+                }
+                for a in adjacents([x, y], self.boundary, self.size) {
+                    let other = grid.props_at(a);
+                    if other.code != AIR && other.phase != Phase::Static {
+                        grid.set(a, Pixel::spawn("air".to_string()));
+                    }
+                }
+            }
+        }
+    }
+
+    //push everything within `radius` of `center` outwards, weaker the further
+    //out it is, static pixels that can't take the force are blown away
+    pub fn apply_impulse(&mut self, center: [u32; 2], radius: u32, strength: f64) {
+        impulse(
+            &mut self.grid,
+            center,
+            radius,
+            strength,
+            self.boundary,
+            self.size,
+        );
+    }
+
+    pub fn check_interacts(&self, pos: [u32; 2], grid: &Grid) -> (String, String, [u32; 2]) {
         //check if pixel has an interaction, if none return "none", else return replacemnt type
         //input, (catalyst, output)
-        let elem = grid[pos[1] as usize][pos[0] as usize].ptype;
+        let elem = grid.ptype_at(pos);
 
         if self.elements.interactivity.contains_key(&elem) {
-            let adj = adjacents(pos, self.edge_mode, self.size);
+            let adj = adjacents(pos, self.boundary, self.size);
             let interactions = self.elements.interactivity.get(&elem).unwrap();
 
             for a in adj {
-                let adj_elem = grid[a[1] as usize][a[0] as usize].ptype;
+                let adj_elem = grid.ptype_at(a);
                 for interact in interactions {
                     if interact.0 == adj_elem {
                         //interaction found, does the catalyst reciprocate?
@@ -204,38 +628,15 @@ impl Simulation {
                 }
             }
         }
-        return ("none".to_string(), "none".to_string(), [0, 0]);
-    }
-
-    pub fn _empty_check(&self, grid: Vec<Vec<Pixel>>) {
-        //temp check, make sure no type 0 pixels
-        for y in 0..self.size[1] {
-            for x in 0..self.size[0] {
-                if grid[y as usize][x as usize].ptype == 0 {
-                    println!("ERROR: TYPE 0 PIXEL AT {:?} {:?}", x, y);
-                }
-            }
-        }
-    }
-
-    pub fn grid_pos(&self, id: u32, pos: [u32; 2]) -> [u32; 2] {
-        let x = pos[0];
-        let y = pos[1];
-
-        let [xi, yi] = id_coord(id, (self.chunk_div as f64).sqrt() as u32);
-
-        [
-            y + yi * self.size[1] / (self.chunk_div as f64).sqrt() as u32,
-            x + xi * self.size[0] / (self.chunk_div as f64).sqrt() as u32,
-        ]
+        ("none".to_string(), "none".to_string(), [0, 0])
     }
 
-    pub fn print(&self, _verbose: bool) {
+    pub fn print(&self) {
         let mut stdout = stdout();
         stdout.queue(cursor::SavePosition).unwrap();
         for y in 0..self.size[1] {
             print!("{:04}|", y);
-            for x in 0..self.size[0] {
+            for _x in 0..self.size[0] {
                 //print!("{}", self.grid[y as usize][x as usize].print());
             }
             println!();
@@ -248,9 +649,17 @@ impl Simulation {
 
     //place pixel (sand) at mouse position
     pub fn place_pixel(&mut self, typ: String) {
-        let x = self.mouse_pos[0];
-        let y = self.mouse_pos[1];
-        self.grid[y as usize][x as usize] = Pixel::spawn(typ, [x, y]);
+        self.set_pixel(self.mouse_pos, typ);
+    }
+
+    //replace the pixel at pos, keeping the emitter table in step
+    pub fn set_pixel(&mut self, pos: [u32; 2], typ: String) {
+        if typ == "emitter" {
+            self.emitters.insert(pos, self.emitter);
+        } else {
+            self.emitters.remove(&pos);
+        }
+        self.grid.set(pos, Pixel::spawn(typ));
     }
 
     //place pixel (stone) from mouse position to mouse position
@@ -259,7 +668,7 @@ impl Simulation {
         //println!("{:?}", rect);
         for x in rect[0]..rect[2] + 1 {
             for y in rect[1]..rect[3] + 1 {
-                self.grid[y as usize][x as usize] = Pixel::spawn(typ.clone(), [x, y]);
+                self.set_pixel([x, y], typ.clone());
             }
         }
     }
@@ -270,13 +679,27 @@ impl Simulation {
         let y = self.mouse_pos[1];
         for x in 0..self.size[0] {
             for y in y..self.size[1] {
-                if self.grid[y as usize][x as usize].density <= 0.3 {
-                    self.grid[y as usize][x as usize] = Pixel::spawn(typ.clone(), [x, y]);
+                if self.grid.props_at([x, y]).density <= 0.3 {
+                    self.set_pixel([x, y], typ.clone());
                 }
             }
         }
     }
 
+    //cycle what newly placed emitters spawn through the non static elements,
+    //returns the name of the new one
+    pub fn next_emitter_element(&mut self) -> String {
+        let mut code = self.emitter.element;
+        loop {
+            code = code % (self.elements._len() as u8 - 1) + 1;
+            if self.elements.props[code as usize].phase != Phase::Static {
+                break;
+            }
+        }
+        self.emitter.element = code;
+        self.elements.get_name(code)
+    }
+
     //pixel radius iterator
     pub fn radius_iter(&mut self, r: u32) -> Vec<[u32; 2]> {
         radius(self.mouse_pos, r, self.size)
@@ -287,7 +710,7 @@ impl Simulation {
         let _x = self.mouse_pos[0];
         let _y = self.mouse_pos[1];
         for pixel in self.radius_iter(r) {
-            self.grid[pixel[1] as usize][pixel[0] as usize] = air([pixel[0], pixel[1]]);
+            self.set_pixel(pixel, "air".to_string());
         }
     }
 
@@ -300,7 +723,7 @@ impl Simulation {
     //[#] = brick
 }
 
-pub fn id_coord(id: u32, scale: u32) -> [u32; 2] {
+pub fn _id_coord(id: u32, scale: u32) -> [u32; 2] {
     //println!("IDING {:?} {:?}", id, scale);
     let x = id % scale;
     let y = (id - x) / scale;
@@ -359,51 +782,120 @@ pub fn check_coord(pos: [i32; 2], size: [u32; 2]) -> bool {
     }
 }
 
-pub fn wrapped_coord(pos: [i32; 2], edge_mode: bool, size: [u32; 2]) -> [u32; 2] {
-    let bx = size[0] as i32 - 1;
-    let by = size[1] as i32 - 1;
-    //println!("SHTUFF {}/{}", bx, by);
-    let mut x = pos[0];
-    let mut y = pos[1];
-    if edge_mode {
-        //go to other side of grid
-        if x < 0 {
-            x = bx;
-        } else if x > bx {
-            x = 0;
-        }
-        if y < 0 {
-            y = by;
-        } else if y > by {
-            y = 0;
+//wrap `pos` around the axes that wrap, however far out it is,
+//other axes are left as they are
+pub fn wrap(pos: [i32; 2], boundary: [Boundary; 2], size: [u32; 2]) -> [i32; 2] {
+    let mut pos = pos;
+    for axis in 0..2 {
+        if boundary[axis] == Boundary::Wrap {
+            pos[axis] = pos[axis].rem_euclid(size[axis] as i32);
         }
-    } else {
-        //stay on same side of grid
-        if x < 0 {
-            x = 0;
-        } else if x > bx {
-            x = bx;
+    }
+    pos
+}
+
+//the cell `pos` ends up in, wrapping axes go round to the other side and
+//everything else stops at the last cell
+pub fn wrapped_coord(pos: [i32; 2], boundary: [Boundary; 2], size: [u32; 2]) -> [u32; 2] {
+    let pos = wrap(pos, boundary, size);
+    [
+        pos[0].clamp(0, size[0] as i32 - 1) as u32,
+        pos[1].clamp(0, size[1] as i32 - 1) as u32,
+    ]
+}
+
+//out of 256, how much heat a cell keeps from one tick to the next
+const HEAT_KEPT: u32 = 192;
+//heat at which fuel with flammability 1.0 is sure to catch
+const IGNITE_HEAT: f64 = 128.0;
+
+//whether moving from `at` by `dir` takes a pixel out of the world through
+//an edge that lets it go, open edges only let out what would move into the
+//air outside
+pub fn leaves(
+    pix: &ElementProps,
+    at: [u32; 2],
+    dir: [i32; 2],
+    grid: &Grid,
+    boundary: [Boundary; 2],
+    size: [u32; 2],
+) -> bool {
+    //air leaving would only be swapped for more air
+    if pix.code == AIR {
+        return false;
+    }
+    let air = grid.element(AIR);
+    let to = [at[0] as i32 + dir[0], at[1] as i32 + dir[1]];
+    (0..2).any(|axis| {
+        if to[axis] >= 0 && to[axis] < size[axis] as i32 {
+            return false;
+        }
+        match boundary[axis] {
+            Boundary::Void => true,
+            //up is the only way lighter things go
+            Boundary::Open if axis == 1 && to[1] < 0 => air.displaces(pix),
+            Boundary::Open => pix.displaces(air),
+            _ => false,
         }
-        if y < 0 {
-            y = 0;
-        } else if y > by {
-            y = by;
+    })
+}
+
+//how much harder than its min_force a static pixel has to be hit to break
+const BLAST_TOUGHNESS: f64 = 4.0;
+//speed sideways or upwards past which a pixel flies along its velocity
+//instead of following its movement rule
+const FLUNG_SPEED: f64 = 0.5;
+
+pub fn impulse(
+    grid: &mut Grid,
+    center: [u32; 2],
+    radius: u32,
+    strength: f64,
+    boundary: [Boundary; 2],
+    size: [u32; 2],
+) {
+    let r = radius as i32;
+    for dy in -r..r + 1 {
+        for dx in -r..r + 1 {
+            let dist = ((dx * dx + dy * dy) as f64).sqrt();
+            if dist > radius as f64 {
+                continue;
+            }
+            let raw = [center[0] as i32 + dx, center[1] as i32 + dy];
+            let raw = wrap(raw, boundary, size);
+            if !check_coord(raw, size) {
+                continue;
+            }
+            let pos = [raw[0] as u32, raw[1] as u32];
+            let i = grid.index(pos);
+            let pix = *grid.props(i);
+
+            let force = strength * (1.0 - dist / (radius as f64 + 1.0));
+            if pix.phase == Phase::Static {
+                if force > pix.min_force * BLAST_TOUGHNESS {
+                    grid.set(pos, Pixel::spawn("air".to_string()));
+                }
+                continue;
+            }
+            if dist > 0.0 {
+                let mut vel = grid.velocity(i);
+                vel[0] += dx as f64 / dist * force;
+                vel[1] += dy as f64 / dist * force;
+                grid.set_velocity(i, vel);
+            }
         }
     }
-    [x as u32, y as u32]
 }
 
-pub fn adjacents(pos: [u32; 2], edge_mode: bool, size: [u32; 2]) -> Vec<[u32; 2]> {
+pub fn adjacents(pos: [u32; 2], boundary: [Boundary; 2], size: [u32; 2]) -> Vec<[u32; 2]> {
     let mut vec = Vec::new();
 
     for i in -1..2 {
         for j in -1..2 {
             if i != 0 || j != 0 {
-                let x = pos[0] as i32 + i;
-                let y = pos[1] as i32 + j;
-                let coord = wrapped_coord([x, y], edge_mode, size);
-                if coord != pos {
-                    vec.push(coord);
+                let coord = wrap([pos[0] as i32 + i, pos[1] as i32 + j], boundary, size);
+                if check_coord(coord, size) && coord != [pos[0] as i32, pos[1] as i32] {
+                    vec.push([coord[0] as u32, coord[1] as u32]);
                 }
             }
         }
@@ -411,128 +903,1002 @@ pub fn adjacents(pos: [u32; 2], edge_mode: bool, size: [u32; 2]) -> Vec<[u32; 2]
     vec
 }
 
-pub fn subdate(
-    sgrid: Vec<Vec<Pixel>>,
-    id: u32,
+pub fn blocked_below(
+    pix: &ElementProps,
+    at: [u32; 2],
+    grid: &Grid,
+    boundary: [Boundary; 2],
+    size: [u32; 2],
+) -> bool {
+    let below = wrapped_coord([at[0] as i32, at[1] as i32 + 1], boundary, size);
+    if below == at {
+        //the floor, unless it lets things fall out
+        return !leaves(pix, at, [0, 1], grid, boundary, size);
+    }
+    !pix.displaces(grid.props_at(below))
+}
+
+//sideways destination of a liquid that can't fall, up to its dispersion distance
+pub fn flow<N: Num>(
+    pix: &ElementProps,
+    at: [u32; 2],
+    grid: &Grid,
+    boundary: [Boundary; 2],
+    size: [u32; 2],
+) -> [u32; 2] {
+    let mut rng = random::rng();
+    if N::movement(pix).viscosity.chance() {
+        return at;
+    }
+
+    //towards the side with less pressure, a random one when they're level
+    //so liquids don't drift one way
+    let mut dir: i32 = if rng.gen_bool(0.5) { 1 } else { -1 };
+    let side = |d: i32| {
+        let next = wrapped_coord([at[0] as i32 + d, at[1] as i32], boundary, size);
+        grid.pressure[grid.index(next)]
+    };
+    if side(-dir) < side(dir) {
+        dir = -dir;
+    }
+    for d in [dir, -dir] {
+        let mut dest = at;
+        for step in 1..pix.dispersion as i32 + 1 {
+            let next = wrapped_coord([at[0] as i32 + d * step, at[1] as i32], boundary, size);
+            if next == dest || !pix.displaces(grid.props_at(next)) {
+                break;
+            }
+            dest = next;
+            //stop at the first drop so it can fall in next tick
+            if !blocked_below(pix, dest, grid, boundary, size) {
+                break;
+            }
+        }
+        if dest != at {
+            return dest;
+        }
+    }
+    at
+}
+
+//diagonal destination of a powder that can't fall straight down
+pub fn slide<N: Num>(
+    pix: &ElementProps,
+    at: [u32; 2],
+    grid: &Grid,
+    boundary: [Boundary; 2],
+    size: [u32; 2],
+) -> [u32; 2] {
+    let mut rng = random::rng();
+    if N::movement(pix).stickiness.chance() {
+        return at;
+    }
+
+    let dir: i32 = if rng.gen_bool(0.5) { 1 } else { -1 };
+    for d in [dir, -dir] {
+        let next = wrapped_coord([at[0] as i32 + d, at[1] as i32 + 1], boundary, size);
+        if next[0] != at[0] && next[1] != at[1] && pix.displaces(grid.props_at(next)) {
+            return next;
+        }
+    }
+    at
+}
+
+//gases rise past heavier gases above them and otherwise wander,
+//only trading places with other gases when it keeps the lighter one on top
+pub fn gas_move<N: Num>(
+    pix: &ElementProps,
+    at: [u32; 2],
+    grid: &Grid,
+    boundary: [Boundary; 2],
+    size: [u32; 2],
+) -> [u32; 2] {
+    let mut rng = random::rng();
+    let adj = adjacents(at, boundary, size);
+
+    //pushed towards the lowest pressure gas around it, more likely the
+    //bigger the difference
+    let here = N::of(grid.pressure[grid.index(at)] as f64);
+    let mut low = at;
+    for a in &adj {
+        let other = grid.props_at(*a);
+        if other.phase == Phase::Gas
+            && other.code != pix.code
+            && grid.pressure[grid.index(*a)] < grid.pressure[grid.index(low)]
+        {
+            low = *a;
+        }
+    }
+    let lowest = N::of(grid.pressure[grid.index(low)] as f64);
+    if low != at && ((here - lowest) / here).chance() {
+        return low;
+    }
+
+    //buoyancy, more likely the bigger the difference
+    let above = wrapped_coord([at[0] as i32, at[1] as i32 - 1], boundary, size);
+    let other = grid.props_at(above);
+    if above != at
+        && other.phase == Phase::Gas
+        && other.density > pix.density
+        && ((N::movement(other).density - N::movement(pix).density) / N::movement(other).density)
+            .chance()
+    {
+        return above;
+    }
+
+    //diffusion
+    let dest = adj[rng.gen_range(0..adj.len())];
+    let other = grid.props_at(dest);
+    if other.phase != Phase::Gas || other.code == pix.code {
+        return at;
+    }
+    if (dest[1] > at[1] && pix.density < other.density)
+        || (dest[1] < at[1] && pix.density > other.density)
+    {
+        return at;
+    }
+    //gas spreads from high pressure to low
+    if grid.pressure[grid.index(dest)] > grid.pressure[grid.index(at)] {
+        return at;
+    }
+    dest
+}
+
+//the gas above a liquid when liquid standing at least two cells higher in the
+//same body pushes it up, enough to lift it without overshooting the surface
+//the push came from
+pub fn pushed_up<N: Num>(
+    pix: &ElementProps,
+    at: [u32; 2],
+    grid: &Grid,
+    boundary: [Boundary; 2],
     size: [u32; 2],
-    chunk_div: u32,
-    gravity: f64,
-    friction: f64,
-    edge_mode: bool,
-) -> (Vec<Vec<Pixel>>, Vec<Pixel>, u32) {
-    //mutates the subgrid and returns the edge cases
-    let mut edge_cases: Vec<Pixel> = vec![];
+    c: &Constants<N>,
+) -> Option<[u32; 2]> {
+    if pix.phase != Phase::Liquid {
+        return None;
+    }
+    let above = wrapped_coord([at[0] as i32, at[1] as i32 - 1], boundary, size);
+    if above == at || grid.props_at(above).phase != Phase::Gas {
+        return None;
+    }
+    let lift = N::of(grid.pressure[grid.index(at)] as f64)
+        - N::of(grid.pressure[grid.index(above)] as f64);
+    if lift > c.lift * N::movement(pix).density {
+        Some(above)
+    } else {
+        None
+    }
+}
+
+thread_local! {
+    //body_path's way back to where it started, as big as the biggest grid
+    //searched on this thread, every entry is usize::MAX between searches
+    static CAME_FROM: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+//cells from `at` through its body of liquid to the nearest surface where the
+//pressure is at least two cells of weight lower, the way the body moves
+//along when `at` is pushed up, none if every way there is blocked
+pub fn body_path<N: Num>(
+    at: [u32; 2],
+    grid: &Grid,
+    moved: &[Vec<bool>],
+    boundary: [Boundary; 2],
+    size: [u32; 2],
+    c: &Constants<N>,
+) -> Option<Vec<[u32; 2]>> {
+    let code = grid.ptype_at(at);
+    let drop = c.drop * N::movement(grid.props_at(at)).density;
+    let start = N::of(grid.pressure[grid.index(at)] as f64);
+    CAME_FROM.with(|came_from| {
+        let mut came_from = came_from.borrow_mut();
+        if came_from.len() < grid.ptype.len() {
+            came_from.resize(grid.ptype.len(), usize::MAX);
+        }
+        came_from[grid.index(at)] = grid.index(at);
+        //breadth first, everything queued stays in it so it can be cleared after
+        let mut queue = vec![at];
+        let mut head = 0;
+        let path = 'search: {
+            while head < queue.len() {
+                let pos = queue[head];
+                head += 1;
+                let above = wrapped_coord([pos[0] as i32, pos[1] as i32 - 1], boundary, size);
+                if pos != at
+                    && above != pos
+                    && grid.props_at(above).phase == Phase::Gas
+                    && start - N::of(grid.pressure[grid.index(pos)] as f64) >= drop
+                {
+                    let mut path = vec![pos];
+                    let mut i = grid.index(pos);
+                    while came_from[i] != i {
+                        i = came_from[i];
+                        path.push(grid.pos_of(i));
+                    }
+                    path.reverse();
+                    break 'search Some(path);
+                }
+                for d in [[-1, 0], [1, 0], [0, -1], [0, 1]] {
+                    let next =
+                        wrapped_coord([pos[0] as i32 + d[0], pos[1] as i32 + d[1]], boundary, size);
+                    let n = grid.index(next);
+                    if came_from[n] == usize::MAX
+                        && grid.ptype[n] == code
+                        && !moved[next[1] as usize][next[0] as usize]
+                    {
+                        came_from[n] = grid.index(pos);
+                        queue.push(next);
+                    }
+                }
+            }
+            None
+        };
+        for pos in queue {
+            came_from[grid.index(pos)] = usize::MAX;
+        }
+        path
+    })
+}
+
+//push the liquid at `at` up into the gas above it, the rest of its body
+//shifts a cell along behind it, false if it didn't move
+pub fn push<N: Num>(
+    at: [u32; 2],
+    above: [u32; 2],
+    grid: &mut Grid,
+    moved: &mut [Vec<bool>],
+    boundary: [Boundary; 2],
+    size: [u32; 2],
+    c: &Constants<N>,
+) -> bool {
+    if moved[above[1] as usize][above[0] as usize]
+        || N::movement(grid.props_at(at)).viscosity.chance()
+    {
+        return false;
+    }
+    let Some(path) = body_path(at, grid, moved, boundary, size, c) else {
+        return false;
+    };
+    let i = grid.index(at);
+    N::set_vel(grid, i, [N::ZERO; 2]);
+    N::set_carry(grid, i, [N::ZERO; 2]);
+    grid.swap(at, above);
+    moved[above[1] as usize][above[0] as usize] = true;
+    for pair in path.windows(2) {
+        grid.swap(pair[0], pair[1]);
+        moved[pair[0][1] as usize][pair[0][0] as usize] = true;
+    }
+    let end = path[path.len() - 1];
+    moved[end[1] as usize][end[0] as usize] = true;
+    true
+}
+
+//density rule, heavier pixels sink through lighter non solid ones, always
+//through gas and more readily through liquid the bigger the difference
+pub fn sinks<N: Num>(pix: &ElementProps, other: &ElementProps) -> bool {
+    if !pix.displaces(other) {
+        return false;
+    }
+    if other.phase == Phase::Gas {
+        return true;
+    }
+    let (heavy, light) = (N::movement(pix).density, N::movement(other).density);
+    ((heavy - light) / heavy).chance()
+}
+
+//how far from its cell a pixel can look or move this tick
+pub fn reach(grid: &Grid, at: [u32; 2]) -> u32 {
+    let i = grid.index(at);
+    let vel = grid.velocity(i);
+    (vel[0].abs().max(vel[1].abs()) + 1.0) as u32 + grid.props(i).dispersion as u32 + 1
+}
+
+//what pixels are sorted by before they move
+pub fn momentum<N: Num>(grid: &Grid, at: [u32; 2]) -> N {
+    let i = grid.index(at);
+    let vel = N::vel(grid, i);
+    (vel[0] + vel[1]) * N::movement(grid.props(i)).density
+}
+
+//cells on the straight line from `from` to `to`, not including `from`
+pub fn line_path(from: [i32; 2], to: [i32; 2]) -> Vec<[i32; 2]> {
+    let dx = (to[0] - from[0]).abs();
+    let dy = -(to[1] - from[1]).abs();
+    let sx = (to[0] - from[0]).signum();
+    let sy = (to[1] - from[1]).signum();
+
+    //bresenham
+    let mut err = dx + dy;
+    let mut p = from;
+    let mut path = vec![];
+    while p != to {
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            p[0] += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            p[1] += sy;
+        }
+        path.push(p);
+    }
+    path
+}
+
+//velocities of the pixels in cells a and b after a runs into b, they move off together
+pub fn collide<N: Num>(grid: &Grid, a: usize, b: usize) -> ([N; 2], [N; 2]) {
+    if grid.props(b).phase == Phase::Static {
+        return ([N::ZERO; 2], N::vel(grid, b));
+    }
+    let pix = N::movement(grid.props(a));
+    let other = N::movement(grid.props(b));
+    let (va, vb) = (N::vel(grid, a), N::vel(grid, b));
+    let mass = pix.density + other.density;
+    let v = [0, 1].map(|axis| (va[axis] * pix.density + vb[axis] * other.density) / mass);
+    (v, v)
+}
+
+//move the pixel at `at` to where it wants to go, if the density rule lets it,
+//cells that were moved already this tick are left alone
+pub fn move_pix<N: Num>(
+    at: [u32; 2],
+    grid: &mut Grid,
+    moved: &mut [Vec<bool>],
+    boundary: [Boundary; 2],
+    size: [u32; 2],
+    c: &Constants<N>,
+) {
+    if moved[at[1] as usize][at[0] as usize] {
+        return;
+    }
+    let i = grid.index(at);
+    let pix = *grid.props(i);
+    if pix.code == 0 || pix.phase == Phase::Static {
+        return;
+    }
+
+    if let Some(above) = pushed_up(&pix, at, grid, boundary, size, c) {
+        if push(at, above, grid, moved, boundary, size, c) {
+            return;
+        }
+    }
+
+    //thrown pixels fly, falling ones and ones at rest follow their rule
+    let vel = N::vel(grid, i);
+    let flung = vel[0].abs() > c.flung || -vel[1] > c.flung;
+
+    //gases float out of the top and liquids spill over the sides
+    let spill = match pix.phase {
+        _ if flung => None,
+        Phase::Gas => Some([0, -1]),
+        Phase::Liquid if blocked_below(&pix, at, grid, boundary, size) => {
+            Some([if at[0] == 0 { -1 } else { 1 }, 0])
+        }
+        _ => None,
+    };
+    if let Some(dir) = spill {
+        if leaves(&pix, at, dir, grid, boundary, size) {
+            grid.set(at, Pixel::spawn("air".to_string()));
+            moved[at[1] as usize][at[0] as usize] = true;
+            return;
+        }
+    }
+
+    let rule = if flung {
+        None
+    } else if pix.phase == Phase::Gas {
+        Some(gas_move::<N>(&pix, at, grid, boundary, size))
+    } else if pix.phase == Phase::Liquid && blocked_below(&pix, at, grid, boundary, size) {
+        //liquids spread out when they have nowhere to fall
+        Some(flow::<N>(&pix, at, grid, boundary, size))
+    } else if pix.phase == Phase::Powder && blocked_below(&pix, at, grid, boundary, size) {
+        //powders slide down the side of the pile
+        Some(slide::<N>(&pix, at, grid, boundary, size))
+    } else {
+        None
+    };
+
+    //rules pick a cell they already checked, the pixel comes to rest there
+    if let Some(dest) = rule {
+        if dest == at || moved[dest[1] as usize][dest[0] as usize] {
+            return;
+        }
+        let other = *grid.props_at(dest);
+        if pix.phase != Phase::Gas && !sinks::<N>(&pix, &other) {
+            return;
+        }
+        N::set_vel(grid, i, [N::ZERO; 2]);
+        N::set_carry(grid, i, [N::ZERO; 2]);
+        grid.swap(at, dest);
+        moved[at[1] as usize][at[0] as usize] = true;
+        moved[dest[1] as usize][dest[0] as usize] = true;
+        return;
+    }
+
+    //carry the fraction of a cell left over from earlier ticks
+    let sub = N::carry(grid, i);
+    let travel = [sub[0] + vel[0], sub[1] + vel[1]];
+    let steps = [travel[0].trunc(), travel[1].trunc()];
+    N::set_carry(grid, i, [travel[0] - steps[0], travel[1] - steps[1]]);
+
+    //walk the path a cell at a time, stopping at the first thing in the way
+    let target = [
+        at[0] as i32 + steps[0].to_f64() as i32,
+        at[1] as i32 + steps[1].to_f64() as i32,
+    ];
+    let mut cur = at;
+    let mut last = [at[0] as i32, at[1] as i32];
+    for step in line_path(last, target) {
+        if leaves(
+            &pix,
+            cur,
+            [step[0] - last[0], step[1] - last[1]],
+            grid,
+            boundary,
+            size,
+        ) {
+            grid.set(cur, Pixel::spawn("air".to_string()));
+            moved[cur[1] as usize][cur[0] as usize] = true;
+            break;
+        }
+        let next = wrapped_coord(step, boundary, size);
+        let c = grid.index(cur);
+        //cut a corner into something, slide along it instead
+        if step[0] != last[0] && step[1] != last[1] {
+            let side = wrapped_coord([step[0], last[1]], boundary, size);
+            if side != cur
+                && !moved[side[1] as usize][side[0] as usize]
+                && !sinks::<N>(&pix, grid.props_at(next))
+                && sinks::<N>(&pix, grid.props_at(side))
+            {
+                let (mut vel, mut sub) = (N::vel(grid, c), N::carry(grid, c));
+                vel[1] = N::ZERO;
+                sub[1] = N::ZERO;
+                N::set_vel(grid, c, vel);
+                N::set_carry(grid, c, sub);
+                grid.swap(cur, side);
+                moved[cur[1] as usize][cur[0] as usize] = true;
+                moved[side[1] as usize][side[0] as usize] = true;
+                break;
+            }
+        }
+        last = step;
+        if next == cur {
+            //against the edge of the world
+            N::set_vel(grid, c, [N::ZERO; 2]);
+            N::set_carry(grid, c, [N::ZERO; 2]);
+            break;
+        }
+        if moved[next[1] as usize][next[0] as usize] {
+            break;
+        }
+        let n = grid.index(next);
+        if !sinks::<N>(&pix, grid.props(n)) {
+            //impact, momentum is shared with whatever was hit
+            let (v1, v2) = collide::<N>(grid, c, n);
+            N::set_vel(grid, c, v1);
+            N::set_carry(grid, c, [N::ZERO; 2]);
+            N::set_vel(grid, n, v2);
+            break;
+        }
+        grid.swap(cur, next);
+        moved[cur[1] as usize][cur[0] as usize] = true;
+        moved[next[1] as usize][next[0] as usize] = true;
+        cur = next;
+    }
+}
+
+pub fn subdate<N: Num>(sgrid: Grid, id: u32, c: Constants<N>) -> (Grid, Vec<[u32; 2]>, u32) {
+    //mutates the subgrid and returns the edge cases, as positions in the chunk
+    let mut edge_cases: Vec<[u32; 2]> = vec![];
     //get list of pixels ordered by and velocity
     let mut pixel_list = Vec::new();
 
-    let chunk_id = id_coord(id, (chunk_div as f64).sqrt() as u32);
-
-    let mut subgrid = sgrid.clone();
-
-    //println!("SUBDATE1 LEN{} LEN{}", subgrid.len(), subgrid.len());
-    for y in 0..subgrid.len() {
-        for x in 0..subgrid[y].len() {
-            let mut pix = subgrid[y][x];
-            //add randomness to low force particles
-            let move_chance: f64;
-            let mut rng = rand::thread_rng();
-            if pix.min_force == 0.0 {
-                move_chance = 1.0;
-            } else if pix.min_force < 0.5 {
-                move_chance = rng.gen_range(0.0..1.0 - pix.min_force);
-            } else {
-                move_chance = 0.0;
+    let mut subgrid = sgrid;
+    let csize = subgrid.size;
+
+    for y in 0..csize[1] {
+        for x in 0..csize[0] {
+            let i = subgrid.index([x, y]);
+            let pix = *subgrid.props(i);
+            //static pixels are skipped entirely
+            if pix.phase == Phase::Static {
+                continue;
+            }
+            //nothing gets a random sideways push, powders slide, liquids flow
+            //and gases wander in their own movement rules
+            let mv = N::movement(&pix);
+            let mut vel = N::vel(&subgrid, i);
+            vel = [
+                vel[0] * c.friction * mv.friction_multiplier,
+                vel[1] * c.friction * mv.friction_multiplier,
+            ];
+            //gases rise by buoyancy in gas_move, not gravity, but keep
+            //whatever they were pushed with
+            if pix.phase != Phase::Gas {
+                vel[1] = vel[1] + mv.gravity_multiplier * c.gravity;
             }
 
-            if move_chance > pix.min_force {
-                pix.vel[0] += rng
-                    .gen_range(-gravity * (2.0 - pix.min_force)..gravity * (2.0 - pix.min_force));
-                pix.vel[1] += rng
-                    .gen_range(-gravity * (1.4 - pix.min_force)..gravity * (1.0 - pix.min_force));
+            if mv.min_force > vel[0].abs() {
+                vel[0] = N::ZERO;
+            }
+            if mv.min_force > vel[1].abs() {
+                vel[1] = N::ZERO;
             }
+            N::set_vel(&mut subgrid, i, vel);
 
-            pix.vel = [
-                pix.vel[0] * friction * pix.friction_multiplier,
-                pix.vel[1] * friction * pix.friction_multiplier,
-            ];
+            pixel_list.push([x, y]);
+        }
+    }
+
+    pixel_list.sort_by(|a, b| momentum::<N>(&subgrid, *a).total_cmp(momentum::<N>(&subgrid, *b)));
+
+    //pixels that could reach outside the chunk are left for update_whole
+    let mut moved = vec![vec![false; csize[0] as usize]; csize[1] as usize];
+    let mut border = vec![];
+    for at in pixel_list {
+        //so are pushes, the rest of the body could be anywhere
+        let r = reach(&subgrid, at);
+        let pix = *subgrid.props_at(at);
+        if at[0] < r
+            || at[1] < r
+            || at[0] + r >= csize[0]
+            || at[1] + r >= csize[1]
+            || pushed_up(&pix, at, &subgrid, [Boundary::Wall; 2], csize, &c).is_some()
+        {
+            border.push(at);
+            continue;
+        }
+        move_pix(at, &mut subgrid, &mut moved, [Boundary::Wall; 2], csize, &c);
+    }
+    for at in border {
+        if !moved[at[1] as usize][at[0] as usize] {
+            edge_cases.push(at);
+        }
+    }
 
-            pix.vel[1] += pix.gravity_multiplier * gravity;
+    (subgrid, edge_cases, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Motion;
+
+    fn count(sim: &Simulation, ptype: u8) -> usize {
+        sim.grid.ptype.iter().filter(|code| **code == ptype).count()
+    }
 
-            if pix.min_force > pix.vel[0].abs() {
-                pix.vel[0] = 0.0;
+    #[test]
+    fn sand_sinks_through_water() {
+        let mut sim = Simulation::new();
+        //fill the bottom half with water and drop a grain of sand on top
+        for y in sim.size[1] / 2..sim.size[1] {
+            for x in 0..sim.size[0] {
+                sim.grid.set([x, y], Pixel::spawn("water".to_string()));
             }
-            if pix.min_force > pix.vel[1].abs() {
-                pix.vel[1] = 0.0;
+        }
+        let x = sim.size[0] / 2;
+        let y = sim.size[1] / 2 - 1;
+        sim.grid.set([x, y], Pixel::spawn("sand".to_string()));
+        let water = count(&sim, 3);
+
+        for _ in 0..400 {
+            sim.step();
+        }
+
+        assert_eq!(count(&sim, 2), 1);
+        assert_eq!(count(&sim, 3), water);
+        assert!(sim.grid.row(sim.size[1] - 1).contains(&2));
+    }
+
+    #[test]
+    fn falling_sand_goes_straight_through_air() {
+        let mut sim = Simulation::new();
+        for x in 0..sim.size[0] {
+            sim.grid.set([x, 2], Pixel::spawn("sand".to_string()));
+            let i = sim.grid.index([x, 2]);
+            sim.grid.set_velocity(i, [0.0, 8.0]);
+        }
+
+        sim.step();
+        //8 slowed by friction then sped up by gravity is 9.04, nothing in the way
+        for x in 0..sim.size[0] {
+            assert_eq!(sim.grid.ptype_at([x, 11]), 2);
+        }
+    }
+
+    #[test]
+    fn water_stays_on_sand() {
+        let mut sim = Simulation::new();
+        //stone floor, a layer of sand, water poured on top
+        for x in 0..sim.size[0] {
+            let floor = sim.size[1] - 1;
+            sim.grid.set([x, floor], Pixel::spawn("stone".to_string()));
+            for y in floor - 5..floor {
+                sim.grid.set([x, y], Pixel::spawn("sand".to_string()));
             }
-            //println!("PIXEL VEL: {:?}", pix.vel);
+            for y in floor - 8..floor - 5 {
+                sim.grid.set([x, y], Pixel::spawn("water".to_string()));
+            }
+        }
+
+        for _ in 0..100 {
+            sim.step();
+        }
 
-            pixel_list.push(pix);
+        //water never sinks into the sand below it
+        for y in sim.size[1] - 6..sim.size[1] {
+            assert!(!sim.grid.row(y).contains(&3));
         }
     }
 
-    pixel_list.sort_by(|a, b| {
-        ((a.vel[0] + a.vel[1]) * a.density)
-            .partial_cmp(&((b.vel[0] + b.vel[1]) * b.density))
-            .unwrap()
-    });
+    #[test]
+    fn static_solids_never_move() {
+        let mut sim = Simulation::new();
+        //a floating stone slab with sand piled on it
+        let y = sim.size[1] / 2;
+        for x in 5..15 {
+            sim.grid.set([x, y], Pixel::spawn("stone".to_string()));
+            sim.grid.set([x, y - 1], Pixel::spawn("sand".to_string()));
+        }
 
-    let mut pixel_pairs = Vec::new();
-    for pix in &pixel_list {
-        let pos = [pix.pos[0] as i32, pix.pos[1] as i32];
-        let new_pos = [
-            pix.pos[0] as i32 + pix.vel[0] as i32,
-            pix.pos[1] as i32 + pix.vel[1] as i32,
-        ];
+        for _ in 0..50 {
+            sim.step();
+        }
 
-        if check_coord(new_pos, size) == false || check_coord(pos, size) {
-            edge_cases.push(pix.clone());
-            //println!("    S: EDGE CASE: {:?}", pix.pos);
-            continue;
-            //else if pix is none || pix doesnt move || dest is same type || dest is greater density
+        for x in 5..15 {
+            assert_eq!(sim.grid.ptype_at([x, y]), 5);
         }
-        let dest = sgrid[pos[0] as usize][pos[1] as usize];
-        if pix.ptype == 0 || new_pos == [pos[0] as i32, pos[1] as i32] {
-            //println!("    S: NO MOVE1: {:?}", pix.pos);
-            continue;
-        } else if dest.ptype == pix.ptype || pix.density < dest.density {
-            //println!("    S: NO MOVE2: {:?}", pix.pos);
-            continue;
-        } else {
-            //rintln!("    S: MOVE: {:?} {:?}", pix.pos, new_pos);
-            pixel_pairs.push([pos, new_pos]);
+    }
+
+    #[test]
+    fn sand_column_slumps_into_heap() {
+        let mut sim = Simulation::new();
+        let floor = sim.size[1] - 1;
+        let x = sim.size[0] / 2;
+        for y in floor - 12..floor + 1 {
+            sim.grid.set([x, y], Pixel::spawn("sand".to_string()));
+        }
+
+        for _ in 0..200 {
+            sim.step();
         }
+
+        assert_eq!(count(&sim, 2), 13);
+        //spread out along the floor and no longer a column
+        let base = sim
+            .grid
+            .row(floor)
+            .iter()
+            .filter(|code| **code == 2)
+            .count();
+        assert!(base >= 5);
+        assert_ne!(sim.grid.ptype_at([x, floor - 8]), 2);
     }
 
-    //for every move, swap pixels
-    for pair in &pixel_pairs {
-        //println!("SSG {}/{} {}/{}", pair[0][0], pair[0][1], pair[1][0], pair[1][1]);
-        subgrid = swap_pix(
-            [pair[0][0] as u32, pair[0][1] as u32],
-            [pair[1][0] as u32, pair[1][1] as u32],
-            &mut subgrid,
-            &sgrid,
-        );
+    #[test]
+    fn fast_pixels_stop_at_walls() {
+        let mut sim = Simulation::new();
+        let wall = sim.size[1] - 10;
+        for x in 0..sim.size[0] {
+            sim.grid.set([x, wall], Pixel::spawn("stone".to_string()));
+        }
+        let x = sim.size[0] / 2;
+        sim.grid.set([x, 5], Pixel::spawn("sand".to_string()));
+        //fast enough to skip past the wall in one tick
+        let i = sim.grid.index([x, 5]);
+        sim.grid.set_velocity(i, [0.0, 40.0]);
+
+        for _ in 0..5 {
+            sim.step();
+        }
+
+        for y in wall..sim.size[1] {
+            assert!(!sim.grid.row(y).contains(&2));
+        }
+        assert_eq!(count(&sim, 2), 1);
     }
 
-    return (subgrid, edge_cases, id);
-}
+    #[test]
+    fn u_tube_levels_out() {
+        let mut sim = Simulation::new();
+        let floor = sim.size[1] - 1;
+        let place = |sim: &mut Simulation, typ: &str, x: u32, y: u32| {
+            sim.grid.set([x, y], Pixel::spawn(typ.to_string()));
+        };
+        //two arms joined along the bottom
+        for x in 4..17 {
+            place(&mut sim, "brick", x, floor);
+        }
+        for y in 10..floor {
+            place(&mut sim, "brick", 4, y);
+            place(&mut sim, "brick", 16, y);
+        }
+        for y in 10..floor - 2 {
+            for x in 9..12 {
+                place(&mut sim, "brick", x, y);
+            }
+        }
+        //left arm full, right arm only has the bottom
+        for x in 5..16 {
+            for y in floor - 2..floor {
+                place(&mut sim, "water", x, y);
+            }
+        }
+        for x in 5..9 {
+            for y in 14..floor - 2 {
+                place(&mut sim, "water", x, y);
+            }
+        }
+        let level = |sim: &Simulation, xs: std::ops::Range<u32>| {
+            let mut top = floor;
+            for y in 0..floor {
+                if xs.clone().any(|x| sim.grid.ptype_at([x, y]) == 3) {
+                    top = y;
+                    break;
+                }
+            }
+            top as i32
+        };
+        let start = level(&sim, 12..16) - level(&sim, 5..9);
+
+        for _ in 0..300 {
+            sim.step();
+        }
+
+        let end = level(&sim, 12..16) - level(&sim, 5..9);
+        assert!(end.abs() * 2 < start.abs());
+        assert!(end.abs() <= 1);
+    }
+
+    #[test]
+    fn gas_escapes_a_chamber_through_an_opening() {
+        let count_inside = |sim: &Simulation| {
+            let mut n = 0;
+            for y in 11..19 {
+                for x in 11..19 {
+                    n += (sim.grid.ptype_at([x, y]) == 1) as u32;
+                }
+            }
+            n
+        };
+        for open in [false, true] {
+            let mut sim = Simulation::new();
+            random::seed(5);
+            //thin smoke everywhere, a brick box of denser air inside
+            for y in 0..sim.size[1] {
+                for x in 0..sim.size[0] {
+                    sim.grid.set([x, y], Pixel::spawn("smoke".to_string()));
+                }
+            }
+            for i in 10..20 {
+                for pos in [[i, 10], [i, 19], [10, i], [19, i]] {
+                    sim.grid.set(pos, Pixel::spawn("brick".to_string()));
+                }
+            }
+            for y in 11..19 {
+                for x in 11..19 {
+                    sim.grid.set([x, y], Pixel::spawn("air".to_string()));
+                }
+            }
+            let air = count_inside(&sim);
+            if open {
+                sim.grid.set([19, 14], Pixel::spawn("smoke".to_string()));
+                sim.grid.set([19, 15], Pixel::spawn("smoke".to_string()));
+            }
+
+            for _ in 0..500 {
+                sim.step();
+            }
+
+            if open {
+                assert!(count_inside(&sim) * 2 < air);
+            } else {
+                assert_eq!(count_inside(&sim), air);
+            }
+        }
+    }
+
+    #[test]
+    fn impulse_pushes_outwards_and_breaks_weak_solids() {
+        let mut sim = Simulation::new();
+        let c = [20, 20];
+        //sand at rest on a shelf
+        for x in 10..19 {
+            sim.grid.set([x, 21], Pixel::spawn("brick".to_string()));
+        }
+        sim.grid.set([17, 20], Pixel::spawn("sand".to_string()));
+        sim.grid.set([21, 20], Pixel::spawn("stone".to_string()));
+        sim.grid.set([24, 20], Pixel::spawn("brick".to_string()));
+        sim.step();
+        assert_eq!(sim.grid.ptype_at([17, 20]), 2);
+
+        sim.apply_impulse(c, 5, 5.0);
+
+        assert!(sim.grid.velocity(sim.grid.index([17, 20]))[0] < 0.0);
+        assert_eq!(sim.grid.ptype_at([21, 20]), 1);
+        assert_eq!(sim.grid.ptype_at([24, 20]), 6);
+
+        sim.step();
+        assert_ne!(sim.grid.ptype_at([17, 20]), 2);
+        assert!((10..17).any(|x| sim.grid.ptype_at([x, 20]) == 2));
+    }
+
+    #[test]
+    fn burning_wood_heats_its_neighbours_and_spreads() {
+        let mut sim = Simulation::new();
+        for x in 5..35 {
+            sim.grid.set([x, 30], Pixel::spawn("wood".to_string()));
+        }
+        let lit = sim.grid.index([5, 30]);
+        assert!(sim.grid.ignite(lit));
+
+        sim.react();
+        assert!(sim.grid.heat[sim.grid.index([6, 30])] > 0);
+        assert_eq!(sim.grid.heat[sim.grid.index([20, 30])], 0);
+
+        for _ in 0..100 {
+            sim.step();
+        }
+        //the fire worked its way along, not all at once
+        let caught = (6..12).any(|x| {
+            let i = sim.grid.index([x, 30]);
+            sim.grid.ptype[i] != 7 || sim.grid.is_burning(i)
+        });
+        assert!(caught);
+        let far = sim.grid.index([34, 30]);
+        assert_eq!(sim.grid.ptype[far], 7);
+        assert!(!sim.grid.is_burning(far));
+    }
+
+    #[test]
+    fn emitters_fill_and_sinks_drain() {
+        let mut sim = Simulation::new();
+        sim.set_pixel([10, 5], "emitter".to_string());
+        sim.set_pixel([30, 39], "sink".to_string());
+
+        for _ in 0..100 {
+            sim.step();
+        }
+        let water = count(&sim, 3);
+        assert!(water > 10);
+
+        //take the emitter away, the sink keeps draining
+        sim.set_pixel([10, 5], "air".to_string());
+        assert!(sim.emitters.is_empty());
+        for _ in 0..400 {
+            sim.step();
+        }
+        assert!(count(&sim, 3) < water);
+    }
+
+    #[test]
+    fn row_brush_registers_emitters() {
+        let mut sim = Simulation::new();
+        sim.mouse_pos = [0, 39];
+        sim.sea("emitter".to_string());
+        assert_eq!(sim.emitters.len(), sim.size[0] as usize);
+        sim.step();
+        assert_eq!(sim.emitters.len(), sim.size[0] as usize);
+    }
+
+    #[test]
+    fn wrapping_handles_any_offset() {
+        let boundary = [Boundary::Wrap, Boundary::Wall];
+        let size = [40, 40];
+        assert_eq!(wrapped_coord([-1, 3], boundary, size), [39, 3]);
+        assert_eq!(wrapped_coord([-81, 3], boundary, size), [39, 3]);
+        assert_eq!(wrapped_coord([125, -7], boundary, size), [5, 0]);
+        assert_eq!(wrapped_coord([40, 99], boundary, size), [0, 39]);
+
+        //round the side, but not past the floor
+        assert_eq!(adjacents([0, 39], boundary, size).len(), 5);
+        assert!(adjacents([0, 39], boundary, size).contains(&[39, 38]));
+    }
+
+    #[test]
+    fn void_floor_swallows_what_falls() {
+        let mut sim = Simulation::new();
+        sim.boundary = [Boundary::Wrap, Boundary::Void];
+        for x in 0..sim.size[0] {
+            sim.grid.set([x, 30], Pixel::spawn("sand".to_string()));
+        }
+        //walls stay put even on the edge
+        sim.set_pixel([5, 39], "stone".to_string());
+
+        for _ in 0..40 {
+            sim.step();
+        }
+        assert_eq!(count(&sim, 2), 0);
+        assert_eq!(count(&sim, 5), 1);
+    }
+
+    #[test]
+    fn fixed_point_runs_ignore_thread_count() {
+        //50 doesn't divide into tiles, the last ones are narrower
+        let run = |chunk_div: u32| {
+            let mut sim = Simulation::with_size([50, 50], chunk_div);
+            sim.math = Math::Fixed;
+            random::seed(5);
+            for x in 0..50 {
+                for y in 0..50 {
+                    let typ = match (x, y) {
+                        (10..=30, 5..=15) => "sand",
+                        (_, 25..=29) if x % 8 < 4 => "lava",
+                        (_, 25..=34) => "water",
+                        (_, 45..) => "wood",
+                        _ => continue,
+                    };
+                    sim.grid.set([x, y], Pixel::spawn(typ.to_string()));
+                }
+            }
+            assert_eq!(sim.tiles().len(), 9);
+            for _ in 0..40 {
+                sim.step();
+            }
+            sim.grid
+        };
+
+        let one = run(1);
+        let Motion::Fixed { vel, sub } = &one.motion else {
+            panic!("fixed point runs keep fixed point velocities");
+        };
+        assert!(vel.iter().chain(sub).any(|v| *v != [Fx(0); 2]));
+        for chunk_div in [4, 16, 4] {
+            let grid = run(chunk_div);
+            assert_eq!(grid.ptype, one.ptype, "{} threads", chunk_div);
+            assert_eq!(grid.life, one.life, "{} threads", chunk_div);
+            let Motion::Fixed { vel: v, sub: s } = &grid.motion else {
+                unreachable!();
+            };
+            assert_eq!((v, s), (vel, sub), "{} threads", chunk_div);
+        }
+    }
 
-pub fn swap_pix(
-    old_pos: [u32; 2],
-    new_pos: [u32; 2],
-    grid: &mut Vec<Vec<Pixel>>,
-    ogrid: &Vec<Vec<Pixel>>,
-) -> Vec<Vec<Pixel>> {
-    //println!("POS SWAP: {:?} {:?}", old_pos, new_pos);
+    #[test]
+    fn open_edges_only_let_out_what_moves_through_air() {
+        let mut sim = Simulation::new();
+        sim.boundary = [Boundary::Wall, Boundary::Open];
+        for x in 0..sim.size[0] {
+            sim.grid.set([x, 0], Pixel::spawn("smoke".to_string()));
+            sim.grid.set([x, 39], Pixel::spawn("sand".to_string()));
+        }
+        //heavier than air, it doesn't float out the top
+        sim.grid.set([3, 0], Pixel::spawn("water".to_string()));
+        sim.step();
+
+        assert_eq!(count(&sim, 2), 0);
+        assert_eq!(count(&sim, 3), 1);
+        //air pushes some smoke back down before it gets out
+        for _ in 0..20 {
+            sim.step();
+        }
+        assert_eq!(count(&sim, 8), 0);
+    }
 
-    let mut pix = ogrid[old_pos[1] as usize][old_pos[0] as usize].clone();
-    pix.pos = [new_pos[0] as u32, new_pos[1] as u32];
-    //println!("SWWWWWWWWWWP {}/{} {}/{}", old_pos[0], old_pos[1], new_pos[0], new_pos[1]);
-    let mut swap = ogrid[new_pos[1] as usize][new_pos[0] as usize];
-    swap.pos = [old_pos[0] as u32, old_pos[1] as u32];
+    #[test]
+    fn open_sides_keep_what_rests_against_them() {
+        let mut sim = Simulation::new();
+        sim.boundary = [Boundary::Open, Boundary::Wall];
+        for y in 30..40 {
+            sim.grid.set([39, y], Pixel::spawn("sand".to_string()));
+        }
+        //nothing holds water in at the side
+        for x in 0..6 {
+            sim.grid.set([x, 39], Pixel::spawn("brick".to_string()));
+        }
+        sim.grid.set([6, 38], Pixel::spawn("brick".to_string()));
+        sim.grid.set([0, 38], Pixel::spawn("water".to_string()));
+        sim.grid.set([4, 38], Pixel::spawn("water".to_string()));
 
-    grid[old_pos[1] as usize][old_pos[0] as usize] = swap;
-    grid[new_pos[1] as usize][new_pos[0] as usize] = pix;
+        for _ in 0..60 {
+            sim.step();
+        }
 
-    grid.to_vec()
+        assert_eq!(count(&sim, 2), 10);
+        assert_eq!(count(&sim, 3), 0);
+    }
 }
@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use crate::elements::*;
+use crate::simulate::*;
+
+//how long each part of the last tick took
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PhaseTimes {
+    pub pressure: Duration,
+    //splitting the grid into chunks
+    pub split: Duration,
+    //subdate on every chunk, threads included
+    pub chunks: Duration,
+    //putting chunks back together and moving edge cases
+    pub whole: Duration,
+    //interactions, burning, emitters and sinks
+    pub react: Duration,
+    pub stats: Duration,
+}
+
+impl PhaseTimes {
+    pub const NAMES: [&'static str; 6] = ["pressure", "split", "chunks", "whole", "react", "stats"];
+
+    pub fn list(&self) -> [Duration; 6] {
+        [
+            self.pressure,
+            self.split,
+            self.chunks,
+            self.whole,
+            self.react,
+            self.stats,
+        ]
+    }
+}
+
+//what the simulation did on its last tick
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub tick: u64,
+    //cells of each element, indexed by element code
+    pub counts: Vec<usize>,
+    //mean speed of everything that isn't static
+    pub avg_vel: f64,
+    //cells that moved or changed element this tick, or are still moving
+    pub active: usize,
+    //reactions and ignitions
+    pub reactions: usize,
+    //type 0 pixels left in the grid, should always be 0
+    pub empty: usize,
+    //cells that reacted or caught fire
+    pub reacted: Vec<[u32; 2]>,
+    //tiles where anything changed or was moving, in Simulation::tiles order
+    pub active_chunks: Vec<bool>,
+    pub times: PhaseTimes,
+}
+
+impl Stats {
+    pub fn csv_header(elements: &ElementList) -> String {
+        let mut cols = vec!["tick".to_string()];
+        for code in 1..elements._len() as u8 {
+            cols.push(elements.get_name(code));
+        }
+        cols.extend(
+            ["avg_vel", "active", "reactions", "empty"]
+                .iter()
+                .map(|col| col.to_string()),
+        );
+        cols.join(",")
+    }
+
+    pub fn csv_row(&self) -> String {
+        let mut cols = vec![self.tick.to_string()];
+        for count in self.counts.iter().skip(1) {
+            cols.push(count.to_string());
+        }
+        cols.push(format!("{:.4}", self.avg_vel));
+        cols.push(self.active.to_string());
+        cols.push(self.reactions.to_string());
+        cols.push(self.empty.to_string());
+        cols.join(",")
+    }
+}
+
+impl Simulation {
+    //cells of each element, indexed by element code
+    pub fn count_elements(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.elements._len()];
+        for ptype in &self.grid.ptype {
+            counts[*ptype as usize] += 1;
+        }
+        counts
+    }
+
+    //recount everything, `before` is the element of each cell at the start of the tick
+    pub fn update_stats(&mut self, before: &[u8]) {
+        let counts = self.count_elements();
+        let mut speed = 0.0;
+        let mut moving = 0;
+        let mut active = 0;
+        //tiles are row by row and all full size but the last row and column
+        let tiles = self.tiles();
+        let side = tiles[0].size;
+        let columns = tiles.iter().filter(|tile| tile.place[1] == 0).count();
+        let mut active_chunks = vec![false; tiles.len()];
+        for (i, ptype) in self.grid.ptype.iter().enumerate() {
+            let [x, y] = self.grid.pos_of(i);
+            let id = (y / side[1]) as usize * columns + (x / side[0]) as usize;
+            let vel = self.grid.velocity(i);
+            if self.grid.props(i).phase != Phase::Static {
+                speed += (vel[0] * vel[0] + vel[1] * vel[1]).sqrt();
+                moving += 1;
+            }
+            if before[i] != *ptype || vel != [0.0; 2] {
+                active += 1;
+                active_chunks[id] = true;
+            }
+        }
+
+        self.stats.tick += 1;
+        self.stats.empty = counts[0];
+        self.stats.counts = counts;
+        self.stats.avg_vel = if moving > 0 {
+            speed / moving as f64
+        } else {
+            0.0
+        };
+        self.stats.active = active;
+        self.stats.active_chunks = active_chunks;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_and_reactions() {
+        let mut sim = Simulation::new();
+        //lava and water boxed in next to each other
+        sim.set_pixel([19, 39], "brick".to_string());
+        sim.set_pixel([20, 39], "lava".to_string());
+        sim.set_pixel([21, 39], "water".to_string());
+        sim.set_pixel([22, 39], "brick".to_string());
+
+        sim.step();
+
+        assert_eq!(sim.stats.tick, 1);
+        assert_eq!(sim.stats.counts.iter().sum::<usize>(), 1600);
+        assert_eq!(sim.stats.counts[6], 2);
+        assert_eq!(sim.stats.empty, 0);
+        assert!(sim.stats.reactions > 0);
+        assert!(sim.stats.active > 0);
+        assert!(sim.stats.reacted.contains(&[20, 39]) || sim.stats.reacted.contains(&[21, 39]));
+        //everything happened in the bottom right chunk
+        assert_eq!(sim.stats.active_chunks, vec![false, false, false, true]);
+        let row = sim.stats.csv_row();
+        let header = Stats::csv_header(&sim.elements);
+        assert_eq!(row.split(',').count(), header.split(',').count());
+    }
+}
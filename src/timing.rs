@@ -0,0 +1,97 @@
+//ticks per update the speed controls step through, below 1.0 is slow motion
+pub const SPEEDS: [f64; 8] = [0.125, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+
+//how many simulation ticks each update event runs, separate from how often frames are drawn
+pub struct TimeScale {
+    pub paused: bool,
+    //index into SPEEDS
+    pub speed: usize,
+    //fraction of a tick carried over from earlier updates
+    owed: f64,
+    //one tick asked for while paused
+    stepping: bool,
+}
+
+impl TimeScale {
+    pub fn new() -> TimeScale {
+        TimeScale {
+            paused: false,
+            speed: 3,
+            owed: 0.0,
+            stepping: false,
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        SPEEDS[self.speed]
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.owed = 0.0;
+    }
+
+    //run one tick on the next update, only does anything while paused
+    pub fn step_once(&mut self) {
+        if self.paused {
+            self.stepping = true;
+        }
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    //ticks to run for this update
+    pub fn ticks(&mut self) -> u32 {
+        if self.paused {
+            let ticks = self.stepping as u32;
+            self.stepping = false;
+            return ticks;
+        }
+        self.owed += self.speed();
+        let ticks = self.owed.floor();
+        self.owed -= ticks;
+        ticks as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_motion_spreads_ticks_out() {
+        let mut time = TimeScale::new();
+        time.slower();
+        time.slower();
+        assert_eq!(time.speed(), 0.25);
+        let ticks: Vec<u32> = (0..8).map(|_| time.ticks()).collect();
+        assert_eq!(ticks, vec![0, 0, 0, 1, 0, 0, 0, 1]);
+
+        time.faster();
+        time.faster();
+        time.faster();
+        assert_eq!(time.ticks(), 2);
+    }
+
+    #[test]
+    fn paused_only_moves_when_stepped() {
+        let mut time = TimeScale::new();
+        time.step_once();
+        assert_eq!(time.ticks(), 1);
+
+        time.toggle_pause();
+        assert_eq!(time.ticks(), 0);
+        time.step_once();
+        assert_eq!(time.ticks(), 1);
+        assert_eq!(time.ticks(), 0);
+
+        time.toggle_pause();
+        assert_eq!(time.ticks(), 1);
+    }
+}
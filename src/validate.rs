@@ -0,0 +1,151 @@
+use crate::simulate::*;
+
+//checks that should hold after every tick, each returns the first thing it
+//finds wrong with the coordinates it's at
+impl Simulation {
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let grid = &self.grid;
+        let n = (self.size[0] * self.size[1]) as usize;
+        let lens = [
+            ("ptype", grid.ptype.len()),
+            ("motion", grid.motion.len()),
+            ("pressure", grid.pressure.len()),
+            ("shade", grid.shade.len()),
+            ("life", grid.life.len()),
+            ("heat", grid.heat.len()),
+        ];
+        for (name, len) in lens {
+            if len != n || grid.size != self.size {
+                return Err(format!(
+                    "grid {} has {} cells, size is {}x{}",
+                    name, len, self.size[0], self.size[1]
+                ));
+            }
+        }
+        for i in 0..n {
+            let [x, y] = grid.pos_of(i);
+            if grid.ptype[i] == 0 {
+                return Err(format!("type 0 pixel at {} {}", x, y));
+            }
+            let (vel, sub) = (grid.velocity(i), grid.carried(i));
+            if !(vel[0].is_finite()
+                && vel[1].is_finite()
+                && sub[0].is_finite()
+                && sub[1].is_finite())
+            {
+                return Err(format!(
+                    "{} at {} {} has velocity {:?}",
+                    self.elements.get_name(grid.ptype[i]),
+                    x,
+                    y,
+                    vel
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    //element counts match `before`, for phases that only move things around
+    pub fn check_conserved(&self, counts: &[usize], before: &[u8]) -> Result<(), String> {
+        let mut expected = vec![0; counts.len()];
+        for ptype in before {
+            expected[*ptype as usize] += 1;
+        }
+        for code in 0..counts.len() {
+            if counts[code] != expected[code] {
+                let first = self.first_changed(code as u8, before);
+                return Err(format!(
+                    "{} count went from {} to {}, first change at {} {}",
+                    self.elements.get_name(code as u8),
+                    expected[code],
+                    counts[code],
+                    first[0],
+                    first[1]
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    //first cell that gained or lost `ptype` since `before`
+    fn first_changed(&self, ptype: u8, before: &[u8]) -> [u32; 2] {
+        for (i, now) in self.grid.ptype.iter().enumerate() {
+            if (*now == ptype) != (before[i] == ptype) {
+                return self.grid.pos_of(i);
+            }
+        }
+        [0, 0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pixel::*;
+
+    use super::*;
+
+    #[test]
+    fn catches_short_arrays() {
+        let mut sim = Simulation::new();
+        assert!(sim.check_invariants().is_ok());
+        sim.grid.life.pop();
+        assert_eq!(
+            sim.check_invariants().unwrap_err(),
+            "grid life has 1599 cells, size is 40x40"
+        );
+    }
+
+    #[test]
+    fn catches_empty_and_bad_velocity() {
+        let mut sim = Simulation::new();
+        sim.grid.set([4, 2], Pixel::default());
+        assert_eq!(sim.check_invariants().unwrap_err(), "type 0 pixel at 4 2");
+
+        let mut sim = Simulation::new();
+        let i = sim.grid.index([6, 5]);
+        sim.grid.set_velocity(i, [0.0, f64::NAN]);
+        assert!(sim.check_invariants().unwrap_err().contains("at 6 5"));
+    }
+
+    #[test]
+    fn catches_lost_pixels() {
+        let mut sim = Simulation::new();
+        sim.set_pixel([9, 9], "sand".to_string());
+        let before = sim.grid.ptype.clone();
+        sim.grid.set([9, 9], Pixel::spawn("air".to_string()));
+
+        let err = sim
+            .check_conserved(&sim.count_elements(), &before)
+            .unwrap_err();
+        assert!(err.contains("at 9 9"));
+    }
+
+    #[test]
+    fn reports_once_instead_of_panicking() {
+        let mut sim = Simulation::new();
+        assert!(!sim.validate);
+        sim.validate = true;
+        sim.grid.set([4, 2], Pixel::default());
+
+        sim.step();
+
+        assert!(sim.broken.take().unwrap().starts_with("tick 1: "));
+        assert!(!sim.validate);
+        sim.step();
+        assert_eq!(sim.broken, None);
+    }
+
+    #[test]
+    fn bad_velocity_is_reported_by_step() {
+        let mut sim = Simulation::new();
+        sim.validate = true;
+        sim.set_pixel([20, 20], "sand".to_string());
+        let i = sim.grid.index([20, 20]);
+        sim.grid.set_velocity(i, [f64::NAN, 0.0]);
+
+        sim.step();
+
+        let broken = sim.broken.unwrap();
+        assert!(broken.contains("velocity"), "{}", broken);
+    }
+}